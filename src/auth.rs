@@ -1,32 +1,87 @@
 use crate::error::{self, Error, Result};
-use serde::Deserialize;
-use std::fmt::Display;
+use crate::transport::{HttpRequest, HttpTransport, Method, ReqwestTransport};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Debug, Display};
+use std::future::Future;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use zeroize::Zeroize;
 
-pub struct ClientSecret(String);
+/// Default OAuth token endpoint of flowmailer.
+pub const TOKEN_URL: &str = "https://login.flowmailer.net/oauth/token";
+
+/// A secret value that is redacted in `Debug` and `Display` output and zeroized on drop.
+/// The value is only available through [`Secret::expose_secret`] and serialization,
+/// which writes the plain value so it can be sent to flowmailer.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+pub struct ClientSecret(Secret);
 
 pub struct ClientId(String);
 
-#[derive(Deserialize, Debug)]
-pub struct AccessToken(String);
+#[derive(Clone, Debug)]
+pub struct AccessToken {
+    secret: Secret,
+    expires_at: Option<Instant>,
+}
 
 #[derive(Deserialize, Debug)]
 struct AccessTokenResponse {
-    access_token: AccessToken,
+    access_token: Secret,
+    expires_in: Option<u64>,
 }
 
 #[derive(Clone, Debug)]
 pub struct AccountId(String);
 
-impl ClientSecret {
+impl Secret {
     pub fn new(secret: String) -> Self {
         Self(secret)
     }
 
-    pub fn secret(&self) -> &String {
+    pub fn expose_secret(&self) -> &str {
         &self.0
     }
 }
 
+impl Drop for Secret {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl Debug for Secret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("[redacted]")
+    }
+}
+
+impl Display for Secret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("[redacted]")
+    }
+}
+
+impl ClientSecret {
+    pub fn new(secret: String) -> Self {
+        Self(Secret::new(secret))
+    }
+
+    pub fn expose_secret(&self) -> &str {
+        self.0.expose_secret()
+    }
+}
+
+impl Debug for ClientSecret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("ClientSecret").field(&self.0).finish()
+    }
+}
+
 impl ClientId {
     pub fn new(id: String) -> Self {
         Self(id)
@@ -38,18 +93,199 @@ impl ClientId {
 }
 
 impl AccessToken {
+    /// Margin before the expiry time from which a token is considered expired.
+    const EXPIRY_MARGIN: Duration = Duration::from_secs(30);
+
+    /// Creates a token that does not expire.
     pub fn new(access_token: String) -> Self {
-        Self(access_token)
+        Self {
+            secret: Secret::new(access_token),
+            expires_at: None,
+        }
     }
 
-    pub fn token(&self) -> &str {
-        &self.0
+    /// Sets the time the token is valid for from now.
+    pub fn expires_in(mut self, valid_for: Duration) -> Self {
+        self.expires_at = Some(Instant::now() + valid_for);
+        self
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| Instant::now() + Self::EXPIRY_MARGIN >= expires_at)
+    }
+
+    pub fn expose_secret(&self) -> &str {
+        self.secret.expose_secret()
     }
 }
 
 impl Display for AccessToken {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", &self.0)
+        write!(f, "{}", &self.secret)
+    }
+}
+
+/// Future returned by [`TokenProvider::token`].
+pub type TokenFuture<'a> = Pin<Box<dyn Future<Output = Result<AccessToken>> + Send + 'a>>;
+
+/// Provides the access tokens a [`Client`](crate::Client) authenticates its requests with.
+///
+/// Implement this to take tokens from e.g. a vault sidecar or a central token broker.
+pub trait TokenProvider: Send + Sync {
+    /// Returns a valid access token.
+    fn token(&self) -> TokenFuture<'_>;
+
+    /// Called when flowmailer rejected the last token, so cached tokens can be dropped.
+    fn invalidate(&self) {}
+}
+
+/// Requests tokens with the OAuth client credentials grant.
+pub struct ClientCredentials {
+    client_id: ClientId,
+    client_secret: ClientSecret,
+    token_url: String,
+    transport: Arc<dyn HttpTransport>,
+}
+
+impl ClientCredentials {
+    pub fn new(client_id: ClientId, client_secret: ClientSecret) -> Self {
+        Self {
+            client_id,
+            client_secret,
+            token_url: TOKEN_URL.to_string(),
+            transport: Arc::new(ReqwestTransport::default()),
+        }
+    }
+
+    /// Requests tokens from `token_url` instead of [`TOKEN_URL`].
+    pub fn token_url(mut self, token_url: impl Into<String>) -> Self {
+        self.token_url = token_url.into();
+        self
+    }
+
+    /// Requests tokens through `transport`.
+    pub fn transport(mut self, transport: impl HttpTransport + 'static) -> Self {
+        self.transport = Arc::new(transport);
+        self
+    }
+
+    /// Shares a transport, and with that its connection pool, with a client.
+    pub(crate) fn shared_transport(mut self, transport: Arc<dyn HttpTransport>) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    async fn request_token(&self) -> Result<AccessToken> {
+        let form = serde_urlencoded::to_string([
+            ("client_id", self.client_id.id().as_str()),
+            ("client_secret", self.client_secret.expose_secret()),
+            ("grant_type", "client_credentials"),
+            ("scope", "api"),
+        ])
+        .map_err(|err| Error::new(error::Kind::Auth(err.to_string())))?;
+        let request = HttpRequest {
+            method: Method::Post,
+            url: self.token_url.clone(),
+            headers: vec![(
+                "Content-Type".to_string(),
+                "application/x-www-form-urlencoded".to_string(),
+            )],
+            body: Some(form.into_bytes()),
+        };
+        let response = self.transport.send(request).await?;
+        if !response.is_success() {
+            return Err(Error::new(error::Kind::Auth(format!(
+                "token request failed with status {}: {}",
+                response.status,
+                String::from_utf8_lossy(&response.body),
+            ))));
+        }
+        let response = match serde_json::from_slice::<AccessTokenResponse>(&response.body) {
+            Ok(json) => json,
+            Err(err) => {
+                return Err(Error::new(error::Kind::Auth(format!(
+                    "failed to parse response json: {err}",
+                ))))
+            }
+        };
+        let token = AccessToken {
+            secret: response.access_token,
+            expires_at: None,
+        };
+        Ok(match response.expires_in {
+            Some(seconds) => token.expires_in(Duration::from_secs(seconds)),
+            None => token,
+        })
+    }
+}
+
+impl TokenProvider for ClientCredentials {
+    fn token(&self) -> TokenFuture<'_> {
+        Box::pin(self.request_token())
+    }
+}
+
+impl From<Auth> for ClientCredentials {
+    fn from(auth: Auth) -> Self {
+        Self::new(auth.client_id, auth.client_secret)
+    }
+}
+
+/// Always provides the same token, e.g. in tests.
+pub struct StaticToken(AccessToken);
+
+impl StaticToken {
+    pub fn new(access_token: impl Into<String>) -> Self {
+        Self(AccessToken::new(access_token.into()))
+    }
+}
+
+impl TokenProvider for StaticToken {
+    fn token(&self) -> TokenFuture<'_> {
+        Box::pin(std::future::ready(Ok(self.0.clone())))
+    }
+}
+
+/// Reuses the tokens of another provider until they expire or are invalidated.
+pub struct CachingTokenProvider<P> {
+    inner: P,
+    cached: Mutex<Option<AccessToken>>,
+}
+
+impl<P: TokenProvider> CachingTokenProvider<P> {
+    pub fn new(inner: P) -> Self {
+        Self {
+            inner,
+            cached: Mutex::new(None),
+        }
+    }
+
+    fn cached(&self) -> Option<AccessToken> {
+        let cached = self.cached.lock().unwrap_or_else(|err| err.into_inner());
+        cached.clone().filter(|token| !token.is_expired())
+    }
+
+    fn store(&self, token: Option<AccessToken>) {
+        *self.cached.lock().unwrap_or_else(|err| err.into_inner()) = token;
+    }
+}
+
+impl<P: TokenProvider> TokenProvider for CachingTokenProvider<P> {
+    fn token(&self) -> TokenFuture<'_> {
+        Box::pin(async move {
+            if let Some(token) = self.cached() {
+                return Ok(token);
+            }
+            let token = self.inner.token().await?;
+            self.store(Some(token.clone()));
+            Ok(token)
+        })
+    }
+
+    fn invalidate(&self) {
+        self.store(None);
+        self.inner.invalidate();
     }
 }
 
@@ -63,6 +299,25 @@ impl AccountId {
     }
 }
 
+impl From<String> for AccountId {
+    fn from(id: String) -> Self {
+        Self(id)
+    }
+}
+
+impl From<&str> for AccountId {
+    fn from(id: &str) -> Self {
+        Self(id.to_string())
+    }
+}
+
+/// Environment variable holding the client ID.
+pub const CLIENT_ID_VAR: &str = "FLOWMAILER_CLIENT_ID";
+/// Environment variable holding the client secret.
+pub const CLIENT_SECRET_VAR: &str = "FLOWMAILER_CLIENT_SECRET";
+/// Environment variable holding the account ID.
+pub const ACCOUNT_ID_VAR: &str = "FLOWMAILER_ACCOUNT_ID";
+
 pub struct Auth {
     client_secret: ClientSecret,
     client_id: ClientId,
@@ -71,37 +326,65 @@ pub struct Auth {
 
 impl Auth {
     pub fn new(client_secret: ClientSecret, client_id: ClientId, account_id: AccountId) -> Self {
-        let auth = Self {
+        Self {
             client_secret,
             client_id,
             account_id,
-        };
+        }
+    }
+
+    /// Loads credentials from the `FLOWMAILER_CLIENT_ID`, `FLOWMAILER_CLIENT_SECRET` and
+    /// `FLOWMAILER_ACCOUNT_ID` environment variables.
+    pub fn from_env() -> Result<Self> {
+        Self::from_lookup(|name| std::env::var(name).ok())
+    }
+
+    /// Loads credentials from a file of `NAME=value` lines using the same names as
+    /// [`Auth::from_env`]. Empty lines and lines starting with `#` are ignored.
+    ///
+    /// # Example
+    /// ```text
+    /// # flowmailer production account
+    /// FLOWMAILER_CLIENT_ID=0123456789abcdef
+    /// FLOWMAILER_CLIENT_SECRET=fedcba9876543210
+    /// FLOWMAILER_ACCOUNT_ID=1234
+    /// ```
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let mut contents = std::fs::read_to_string(path).map_err(|err| {
+            Error::new(error::Kind::Auth(format!(
+                "failed to read credentials file '{}': {err}",
+                path.display()
+            )))
+        })?;
+        let auth = Self::from_contents(&contents);
+        contents.zeroize();
         auth
     }
 
-    pub(crate) async fn authenticate(&self, reqw_client: &reqwest::Client) -> Result<AccessToken> {
-        let reqw = reqw_client
-            .post("https://login.flowmailer.net/oauth/token")
-            .form(&[
-                ("client_id", self.client_id.id().as_str()),
-                ("client_secret", self.client_secret.secret().as_str()),
-                ("grant_type", "client_credentials"),
-                ("scope", "api"),
-            ]);
-        let response = match reqw.send().await {
-            Ok(response) => response,
-            Err(error) => return Err(Error::new(error::Kind::Other(error.to_string()))),
-        };
-        let response = match response.json::<AccessTokenResponse>().await {
-            Ok(json) => json,
-            Err(err) => {
-                return Err(Error::new(error::Kind::Auth(format!(
-                    "failed to parse response json: {}",
-                    err.to_string(),
-                ))))
-            }
+    pub(crate) fn from_contents(contents: &str) -> Result<Self> {
+        Self::from_lookup(|name| {
+            contents
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .filter_map(|line| line.split_once('='))
+                .find(|(key, _)| key.trim() == name)
+                .map(|(_, value)| value.trim().trim_matches('"').to_string())
+        })
+    }
+
+    fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        let get = |name| {
+            lookup(name)
+                .filter(|value| !value.is_empty())
+                .ok_or_else(|| Error::new(error::Kind::Auth(format!("{name} is not set"))))
         };
-        Ok(response.access_token)
+        Ok(Self::new(
+            ClientSecret::new(get(CLIENT_SECRET_VAR)?),
+            ClientId::new(get(CLIENT_ID_VAR)?),
+            AccountId::new(get(ACCOUNT_ID_VAR)?),
+        ))
     }
 
     pub fn account_id(&self) -> &AccountId {
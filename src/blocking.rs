@@ -0,0 +1,285 @@
+//! Synchronous wrapper around [`crate::Client`], for tools and workers without an async runtime.
+//!
+//! Every [`rest_api`](crate::rest_api) call has a blocking version with the same arguments in
+//! [`rest_api`], which drives it to completion on a runtime owned by the client. Other async
+//! calls can be run with [`Client::run`]. Do not use this from within an async context.
+//!
+//! # Example
+//! ```no_run
+//! use flowmailer::{blocking, Auth};
+//!
+//! # fn example() -> flowmailer::Result<()> {
+//! let client = blocking::Client::new(Auth::from_env()?)?;
+//! let flows = blocking::rest_api::flows::list(&client, false)?;
+//! let rendered = client.run(|client| async move {
+//!     flowmailer::Template::get(client, "1").await?.render(serde_json::json!({}))
+//! })?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::auth::{AccountId, Auth, TokenProvider};
+use crate::error::{self, Error, Result};
+use crate::outbox::DeliveryMode;
+use crate::transport::HttpTransport;
+use std::future::Future;
+use std::sync::Arc;
+use tokio::runtime::{Builder, Runtime};
+
+/// Blocking flowmailer API client, see [`crate::Client`].
+#[derive(Clone)]
+pub struct Client {
+    inner: crate::Client,
+    runtime: Arc<Runtime>,
+}
+
+impl Client {
+    /// See [`crate::Client::new`].
+    pub fn new(credentials: Auth) -> Result<Self> {
+        Self::from_async(crate::Client::new(credentials))
+    }
+
+    /// See [`crate::Client::with_transport`].
+    pub fn with_transport(
+        credentials: Auth,
+        transport: impl HttpTransport + 'static,
+    ) -> Result<Self> {
+        Self::from_async(crate::Client::with_transport(credentials, transport))
+    }
+
+    /// See [`crate::Client::with_token_provider`].
+    pub fn with_token_provider(
+        account_id: AccountId,
+        tokens: impl TokenProvider + 'static,
+    ) -> Result<Self> {
+        Self::from_async(crate::Client::with_token_provider(account_id, tokens))
+    }
+
+    /// Wraps an async client.
+    pub fn from_async(inner: crate::Client) -> Result<Self> {
+        let runtime = Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|err| {
+                Error::new(error::Kind::Other(format!(
+                    "failed to start runtime: {err}"
+                )))
+            })?;
+        Ok(Self {
+            inner,
+            runtime: Arc::new(runtime),
+        })
+    }
+
+    /// See [`crate::Client::account`].
+    pub fn account(&self, account_id: impl Into<AccountId>) -> Self {
+        Self {
+            inner: self.inner.account(account_id),
+            runtime: Arc::clone(&self.runtime),
+        }
+    }
+
+    /// See [`crate::Client::with_source`].
+    pub fn with_source(mut self, source_id: impl Into<String>, credentials: Auth) -> Self {
+        self.inner = self.inner.with_source(source_id, credentials);
+        self
+    }
+
+    /// See [`crate::Client::with_source_token_provider`].
+    pub fn with_source_token_provider(
+        mut self,
+        source_id: impl Into<String>,
+        tokens: impl TokenProvider + 'static,
+    ) -> Self {
+        self.inner = self.inner.with_source_token_provider(source_id, tokens);
+        self
+    }
+
+    /// See [`crate::Client::delivery_mode`].
+    pub fn delivery_mode(mut self, delivery: DeliveryMode) -> Self {
+        self.inner = self.inner.delivery_mode(delivery);
+        self
+    }
+
+    pub fn account_id(&self) -> &AccountId {
+        self.inner.account_id()
+    }
+
+    /// Returns the async client this client wraps.
+    pub fn as_async(&self) -> &crate::Client {
+        &self.inner
+    }
+
+    /// Runs an async call with the wrapped client and blocks until it completes.
+    pub fn run<'a, F, Fut>(&'a self, call: F) -> Fut::Output
+    where
+        F: FnOnce(&'a crate::Client) -> Fut,
+        Fut: Future,
+    {
+        self.block_on(call(&self.inner))
+    }
+
+    /// Blocks until `future` completes.
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+}
+
+/// Blocking versions of every [`crate::rest_api`] call, with the same arguments.
+pub mod rest_api {
+    use super::Client;
+    use crate::error::Result;
+    use crate::request::Response;
+    use crate::resources::*;
+    use crate::rest_api::{self as api, Page};
+    use crate::ty;
+
+    /// Generates a module of functions that run the async function of the same name
+    /// in [`crate::rest_api`] on the client's runtime.
+    macro_rules! blocking {
+        ($(mod $module:ident {
+            $(fn $name:ident($($arg:ident: $ty:ty),*) -> $ret:ty;)*
+            $(extra { $($extra:item)* })?
+        })*) => {
+            $(
+                pub mod $module {
+                    #[allow(unused_imports)]
+                    use super::*;
+
+                    $(
+                        #[doc = concat!("See [`api::", stringify!($module), "::", stringify!($name), "`].")]
+                        pub fn $name(client: &Client, $($arg: $ty),*) -> $ret {
+                            client.run(|client| api::$module::$name(client, $($arg),*))
+                        }
+                    )*
+                    $($($extra)*)?
+                }
+            )*
+        };
+    }
+
+    blocking! {
+        mod message {
+            fn submit(msg: SubmitMessage) -> Result<Response>;
+            fn get(message_id: &str, add_events: bool) -> Result<Message>;
+            fn simulate(msg: SimulateMessage) -> Result<SimulateMessageResult>;
+            fn submit_from(source_id: &str, msg: SubmitMessage) -> Result<Response>;
+        }
+        mod stats {
+            fn account(params: &api::stats::Params) -> Result<DataSets>;
+            fn message_stats(params: &api::stats::Params) -> Result<DataSets>;
+        }
+        mod flows {
+            fn list(statistics: bool) -> Result<Vec<Flow>>;
+            fn get(flow_id: &str, statistics: bool) -> Result<Flow>;
+            fn create(flow: Flow) -> Result<Flow>;
+            fn update(flow_id: &str, flow: Flow) -> Result<Flow>;
+            fn delete(flow_id: &str) -> Result<()>;
+            fn stats(flow_id: &str, params: &api::stats::Params) -> Result<DataSets>;
+        }
+        mod sources {
+            fn list(statistics: bool) -> Result<Vec<Source>>;
+            fn get(source_id: &str, statistics: bool) -> Result<Source>;
+            fn create(source: Source) -> Result<Source>;
+            fn update(source_id: &str, source: Source) -> Result<Source>;
+            fn delete(source_id: &str) -> Result<()>;
+            fn stats(source_id: &str, params: &api::stats::Params) -> Result<DataSets>;
+        }
+        mod source_credentials {
+            fn list(source_id: &str) -> Result<Vec<Credentials>>;
+            fn get(source_id: &str, credentials_id: &str) -> Result<Credentials>;
+            fn create(source_id: &str, credentials: Credentials) -> Result<Credentials>;
+            fn update(
+                source_id: &str,
+                credentials_id: &str,
+                credentials: Credentials
+            ) -> Result<Credentials>;
+            fn delete(source_id: &str, credentials_id: &str) -> Result<()>;
+        }
+        mod message_events {
+            fn list(
+                params: &api::message_events::Params,
+                range: &ty::ItemsRange
+            ) -> Result<Page<MessageEvent>>;
+        }
+        mod undelivered_messages {
+            fn list(
+                params: &api::undelivered_messages::Params,
+                range: &ty::ItemsRange
+            ) -> Result<Page<BouncedMessage>>;
+        }
+        mod filters {
+            fn list(params: &api::filters::Params, range: &ty::ItemsRange) -> Result<Page<Filter>>;
+            fn get(address: &str, message_type: Option<ty::MessageType>) -> Result<Vec<Filter>>;
+            fn delete(address: &str, message_type: ty::MessageType) -> Result<()>;
+            extra {
+                /// Blocking version of [`api::filters::export`], as an iterator.
+                pub fn export(
+                    client: &Client,
+                    params: api::filters::Params,
+                    page_size: ty::Int,
+                ) -> impl Iterator<Item = Result<Filter>> + '_ {
+                    let mut export = api::filters::export(client.as_async(), params, page_size);
+                    std::iter::from_fn(move || client.block_on(export.next()))
+                }
+            }
+        }
+        mod recipient {
+            fn get(address: &str) -> Result<Recipient>;
+            fn messages(
+                address: &str,
+                params: &api::recipient::MessagesParams,
+                range: &ty::ItemsRange
+            ) -> Result<Page<Message>>;
+        }
+        mod flow_rules {
+            fn get() -> Result<Vec<FlowRuleSimple>>;
+            fn replace(rules: &[FlowRuleSimple]) -> Result<()>;
+        }
+        mod event_flows {
+            fn list() -> Result<Vec<EventFlow>>;
+            fn get(event_flow_id: &str) -> Result<EventFlow>;
+            fn create(event_flow: EventFlow) -> Result<EventFlow>;
+            fn update(event_flow_id: &str, event_flow: EventFlow) -> Result<EventFlow>;
+            fn delete(event_flow_id: &str) -> Result<()>;
+        }
+        mod event_flow_rules {
+            fn get() -> Result<Vec<EventFlowRuleSimple>>;
+            fn replace(rules: &[EventFlowRuleSimple]) -> Result<()>;
+        }
+        mod flow_templates {
+            fn list() -> Result<Vec<FlowTemplate>>;
+            fn get(flow_template_id: &str) -> Result<FlowTemplate>;
+            fn create(flow_template: FlowTemplate) -> Result<FlowTemplate>;
+            fn update(flow_template_id: &str, flow_template: FlowTemplate) -> Result<FlowTemplate>;
+            fn delete(flow_template_id: &str) -> Result<()>;
+            fn create_flow(
+                flow_template_id: &str,
+                description: impl Into<String>,
+                overrides: impl IntoIterator<Item = FlowStep>
+            ) -> Result<Flow>;
+        }
+        mod templates {
+            fn list() -> Result<Vec<Template>>;
+            fn get(template_id: &str) -> Result<Template>;
+            fn create(template: Template) -> Result<Template>;
+            fn update(template_id: &str, template: Template) -> Result<Template>;
+            fn delete(template_id: &str) -> Result<()>;
+        }
+        mod sender_domains {
+            fn list() -> Result<Vec<SenderDomain>>;
+            fn get(domain_id: &str, validate: bool) -> Result<SenderDomain>;
+            fn by_domain(domain: &str, validate: bool) -> Result<SenderDomain>;
+            fn create(sender_domain: SenderDomain) -> Result<SenderDomain>;
+            fn validate(sender_domain: &SenderDomain) -> Result<SenderDomain>;
+            fn delete(domain_id: &str) -> Result<()>;
+        }
+        mod sender_identities {
+            fn list() -> Result<Vec<SenderIdentity>>;
+            fn get(identity_id: &str) -> Result<SenderIdentity>;
+            fn create(identity: SenderIdentity) -> Result<SenderIdentity>;
+            fn update(identity_id: &str, identity: SenderIdentity) -> Result<SenderIdentity>;
+            fn delete(identity_id: &str) -> Result<()>;
+        }
+    }
+}
@@ -1,29 +1,202 @@
-use crate::auth::{AccessToken, Auth};
+use crate::auth::{
+    AccessToken, AccountId, Auth, CachingTokenProvider, ClientCredentials, TokenProvider,
+};
 use crate::error::Result;
+use crate::outbox::DeliveryMode;
+use crate::transport::{HttpTransport, ReqwestTransport};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
 
+/// Flowmailer API client scoped to one account.
+///
+/// Cloning is cheap: clones and the handles returned by [`Client::account`] share the
+/// connection pool and token cache.
+///
+/// Configure the client before creating handles: [`Client::transport`], [`Client::base_url`],
+/// [`Client::with_source`] and [`Client::with_source_token_provider`] copy the shared state
+/// when other handles exist, so earlier clones and handles keep the old configuration.
+/// [`Client::delivery_mode`] is the exception and applies to all handles.
+#[derive(Clone)]
 pub struct Client {
-    reqw_client: reqwest::Client,
-    credentials: Auth,
+    shared: Arc<Shared>,
+    account_id: AccountId,
+}
+
+#[derive(Clone)]
+struct Shared {
+    base_url: String,
+    delivery: Arc<RwLock<DeliveryMode>>,
+    transport: Arc<dyn HttpTransport>,
+    tokens: Arc<dyn TokenProvider>,
+    sources: HashMap<String, Arc<dyn TokenProvider>>,
 }
 
 impl Client {
+    /// Default base URL of the flowmailer REST API.
+    pub const BASE_URL: &'static str = "https://api.flowmailer.net";
+
+    /// Creates a client that authenticates with the client credentials of `credentials`,
+    /// reusing each token until it expires.
     pub fn new(credentials: Auth) -> Self {
-        let reqw_client = reqwest::Client::new();
+        Self::with_transport(credentials, ReqwestTransport::default())
+    }
+
+    /// Creates a client like [`Client::new`] that sends all requests, including token
+    /// requests, through `transport`.
+    pub fn with_transport(credentials: Auth, transport: impl HttpTransport + 'static) -> Self {
+        let transport: Arc<dyn HttpTransport> = Arc::new(transport);
+        let account_id = credentials.account_id().clone();
+        let tokens = Self::cached_credentials(&transport, credentials);
+        Self::from_parts(transport, account_id, Arc::new(tokens))
+    }
+
+    /// Creates a client that authenticates with tokens from `tokens`.
+    pub fn with_token_provider(
+        account_id: AccountId,
+        tokens: impl TokenProvider + 'static,
+    ) -> Self {
+        Self::from_parts(
+            Arc::new(ReqwestTransport::default()),
+            account_id,
+            Arc::new(tokens),
+        )
+    }
+
+    /// Sends API requests through `transport`.
+    /// Token providers keep the transport they were created with.
+    /// Existing clones and handles keep the previous transport.
+    pub fn transport(mut self, transport: impl HttpTransport + 'static) -> Self {
+        Arc::make_mut(&mut self.shared).transport = Arc::new(transport);
+        self
+    }
+
+    /// Sends API requests to `base_url` instead of [`Client::BASE_URL`], e.g. a mock server.
+    /// Existing clones and handles keep the previous base URL.
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        Arc::make_mut(&mut self.shared).base_url = base_url.into();
+        self
+    }
+
+    /// Sets how mail sent with [`MailBuilder::send`](crate::MailBuilder::send) is delivered.
+    /// Applies to all handles sharing this client's state, including existing ones.
+    pub fn delivery_mode(self, delivery: DeliveryMode) -> Self {
+        *self
+            .shared
+            .delivery
+            .write()
+            .unwrap_or_else(|err| err.into_inner()) = delivery;
+        self
+    }
+
+    fn from_parts(
+        transport: Arc<dyn HttpTransport>,
+        account_id: AccountId,
+        tokens: Arc<dyn TokenProvider>,
+    ) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                base_url: Self::BASE_URL.to_string(),
+                delivery: Arc::default(),
+                transport,
+                tokens,
+                sources: HashMap::new(),
+            }),
+            account_id,
+        }
+    }
+
+    fn cached_credentials(
+        transport: &Arc<dyn HttpTransport>,
+        credentials: Auth,
+    ) -> CachingTokenProvider<ClientCredentials> {
+        CachingTokenProvider::new(
+            ClientCredentials::from(credentials).shared_transport(Arc::clone(transport)),
+        )
+    }
+
+    /// Returns a handle that scopes every call to `account_id`, sharing the connection pool
+    /// and token cache of this client. The credentials must have access to that account.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # async fn example(client: &flowmailer::Client) -> flowmailer::Result<()> {
+    /// use flowmailer::rest_api;
+    ///
+    /// for tenant in ["1234", "5678"] {
+    ///     let flows = rest_api::flows::list(&client.account(tenant), false).await?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn account(&self, account_id: impl Into<AccountId>) -> Self {
         Self {
-            reqw_client,
-            credentials,
+            shared: Arc::clone(&self.shared),
+            account_id: account_id.into(),
         }
     }
 
-    pub fn credentials(&self) -> &Auth {
-        &self.credentials
+    /// Registers the API credentials of a source.
+    /// Messages sent with a source ID are submitted with these credentials,
+    /// so flowmailer attributes them to that source.
+    /// Existing clones and handles do not see the new source.
+    pub fn with_source(self, source_id: impl Into<String>, credentials: Auth) -> Self {
+        let tokens = Self::cached_credentials(&self.shared.transport, credentials);
+        self.with_source_token_provider(source_id, tokens)
+    }
+
+    /// Registers the token provider of a source, see [`Client::with_source`].
+    /// Like all builders except [`Client::delivery_mode`], call this before creating handles.
+    pub fn with_source_token_provider(
+        mut self,
+        source_id: impl Into<String>,
+        tokens: impl TokenProvider + 'static,
+    ) -> Self {
+        Arc::make_mut(&mut self.shared)
+            .sources
+            .insert(source_id.into(), Arc::new(tokens));
+        self
+    }
+
+    pub fn delivery(&self) -> DeliveryMode {
+        self.shared
+            .delivery
+            .read()
+            .unwrap_or_else(|err| err.into_inner())
+            .clone()
+    }
+
+    /// Returns the base URL API requests are sent to.
+    pub fn api_url(&self) -> &str {
+        &self.shared.base_url
+    }
+
+    pub fn account_id(&self) -> &AccountId {
+        &self.account_id
+    }
+
+    /// Returns whether credentials are registered for a source.
+    pub fn has_source(&self, source_id: &str) -> bool {
+        self.shared.sources.contains_key(source_id)
+    }
+
+    pub(crate) fn http(&self) -> &dyn HttpTransport {
+        self.shared.transport.as_ref()
+    }
+
+    /// Returns the token provider of `source_id`, or the client's own when `None`.
+    pub(crate) fn token_provider(&self, source_id: Option<&str>) -> &dyn TokenProvider {
+        match source_id.and_then(|source_id| self.shared.sources.get(source_id)) {
+            Some(tokens) => tokens.as_ref(),
+            None => self.shared.tokens.as_ref(),
+        }
     }
 
-    pub(crate) fn reqw_client(&self) -> &reqwest::Client {
-        &self.reqw_client
+    pub(crate) async fn authenticate(&self, source_id: Option<&str>) -> Result<AccessToken> {
+        self.token_provider(source_id).token().await
     }
 
-    pub(crate) async fn authenticate(&self) -> Result<AccessToken> {
-        self.credentials.authenticate(&self.reqw_client).await
+    #[cfg(test)]
+    pub(crate) fn shares_state_with(&self, other: &Client) -> bool {
+        Arc::ptr_eq(&self.shared, &other.shared)
     }
 }
@@ -0,0 +1,272 @@
+//! Local verification of the DNS records of a sender domain.
+//!
+//! Checks the records flowmailer expects against a [`Resolver`] before asking flowmailer to
+//! validate them. Plug in any DNS client by implementing [`Resolver`]; [`StaticResolver`] serves
+//! fixed records, e.g. in tests. With the `hickory` feature, [`HickoryResolver`] looks records
+//! up with the system's DNS configuration.
+//!
+//! # Example
+//! ```no_run
+//! # #[cfg(feature = "hickory")]
+//! # async fn example(client: &flowmailer::Client) -> flowmailer::Result<()> {
+//! use flowmailer::dns::{self, HickoryResolver};
+//! use flowmailer::rest_api::sender_domains;
+//!
+//! let resolver = HickoryResolver::from_system_conf()?;
+//! let domain = sender_domains::get(client, "1", false).await?;
+//! for record in dns::verify(&resolver, &domain.dns_records).await {
+//!     println!("{} {}: {:?}", record.ty, record.name, record.error_messages);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::Result;
+#[cfg(feature = "hickory")]
+use crate::error::{self, Error};
+use crate::resources::DnsRecord;
+use crate::ty::dns_record::Status;
+use std::collections::HashMap;
+use std::future::Future;
+
+/// Looks up DNS records.
+pub trait Resolver {
+    /// Returns the values of the `record_type` (`TXT`, `CNAME`, `MX`, ...) records of `name`.
+    /// Returns an empty list when there are no such records.
+    fn lookup(
+        &self,
+        name: &str,
+        record_type: &str,
+    ) -> impl Future<Output = Result<Vec<String>>> + Send;
+}
+
+/// Resolver that serves a fixed set of records.
+#[derive(Default)]
+pub struct StaticResolver {
+    records: HashMap<(String, String), Vec<String>>,
+}
+
+impl StaticResolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a record value.
+    pub fn record(
+        mut self,
+        name: impl AsRef<str>,
+        record_type: impl AsRef<str>,
+        value: impl Into<String>,
+    ) -> Self {
+        self.records
+            .entry((
+                normalize_name(name.as_ref()),
+                record_type.as_ref().to_uppercase(),
+            ))
+            .or_default()
+            .push(value.into());
+        self
+    }
+}
+
+impl Resolver for StaticResolver {
+    fn lookup(
+        &self,
+        name: &str,
+        record_type: &str,
+    ) -> impl Future<Output = Result<Vec<String>>> + Send {
+        let values = self
+            .records
+            .get(&(normalize_name(name), record_type.to_uppercase()))
+            .cloned()
+            .unwrap_or_default();
+        std::future::ready(Ok(values))
+    }
+}
+
+/// Resolver backed by [`hickory_resolver`].
+#[cfg(feature = "hickory")]
+pub struct HickoryResolver {
+    resolver: hickory_resolver::TokioAsyncResolver,
+}
+
+#[cfg(feature = "hickory")]
+impl HickoryResolver {
+    /// Creates a resolver using the system's DNS configuration, e.g. `/etc/resolv.conf`.
+    /// Must be called within a tokio runtime.
+    pub fn from_system_conf() -> Result<Self> {
+        let resolver = hickory_resolver::TokioAsyncResolver::tokio_from_system_conf()
+            .map_err(|err| Error::new(error::Kind::Other(format!("dns: {err}"))))?;
+        Ok(Self::from(resolver))
+    }
+}
+
+#[cfg(feature = "hickory")]
+impl From<hickory_resolver::TokioAsyncResolver> for HickoryResolver {
+    fn from(resolver: hickory_resolver::TokioAsyncResolver) -> Self {
+        Self { resolver }
+    }
+}
+
+#[cfg(feature = "hickory")]
+impl Resolver for HickoryResolver {
+    async fn lookup(&self, name: &str, record_type: &str) -> Result<Vec<String>> {
+        use hickory_resolver::error::ResolveErrorKind;
+        use hickory_resolver::proto::rr::{RData, RecordType};
+
+        let dns_error = |err: &dyn std::fmt::Display| {
+            Error::new(error::Kind::Other(format!(
+                "dns: {record_type} lookup of {name} failed: {err}"
+            )))
+        };
+        let record_type: RecordType = record_type
+            .to_uppercase()
+            .parse()
+            .map_err(|err| dns_error(&err))?;
+        let lookup = match self.resolver.lookup(name, record_type).await {
+            Ok(lookup) => lookup,
+            Err(err) if matches!(err.kind(), ResolveErrorKind::NoRecordsFound { .. }) => {
+                return Ok(vec![])
+            }
+            Err(err) => return Err(dns_error(&err)),
+        };
+        Ok(lookup
+            .iter()
+            .map(|data| match data {
+                // TXT values are split into strings of at most 255 bytes
+                RData::TXT(txt) => txt
+                    .iter()
+                    .map(|part| String::from_utf8_lossy(part))
+                    .collect(),
+                data => data.to_string(),
+            })
+            .collect())
+    }
+}
+
+/// Verifies each record and returns it with `status` and `error_messages` filled in.
+pub async fn verify(resolver: &impl Resolver, records: &[DnsRecord]) -> Vec<DnsRecord> {
+    let mut verified = Vec::with_capacity(records.len());
+    for record in records {
+        let mut record = record.clone();
+        let (status, errors) = match resolver.lookup(&record.name, &record.ty).await {
+            Ok(actual) => check(&record, &actual),
+            Err(err) => (Status::Unknown, vec![format!("lookup failed: {err}")]),
+        };
+        record.status = Some(status);
+        record.error_messages = errors.into_boxed_slice();
+        verified.push(record);
+    }
+    verified
+}
+
+fn check(record: &DnsRecord, actual: &[String]) -> (Status, Vec<String>) {
+    let expected = plain_text(&record.value);
+    let record_type = record.ty.to_uppercase();
+    let not_found = || {
+        (
+            Status::Error,
+            vec![format!("no {record_type} record found for {}", record.name)],
+        )
+    };
+    match record_type.as_str() {
+        "CNAME" | "MX" => {
+            let expected = normalize_name(&expected);
+            match actual.first() {
+                None => not_found(),
+                Some(_) if actual.iter().any(|value| target(value) == expected) => {
+                    (Status::Ok, vec![])
+                }
+                Some(value) => (
+                    Status::Error,
+                    vec![format!(
+                        "{record_type} record points to {}, expected {expected}",
+                        normalize_name(value)
+                    )],
+                ),
+            }
+        }
+        "TXT" if expected.starts_with("v=spf1") => {
+            let spf: Vec<_> = actual
+                .iter()
+                .filter(|value| value.starts_with("v=spf1"))
+                .collect();
+            match spf.as_slice() {
+                [] => not_found(),
+                [value] => {
+                    let missing: Vec<_> = expected
+                        .split_whitespace()
+                        .filter(|term| term.starts_with("include:"))
+                        .filter(|term| !value.split_whitespace().any(|actual| actual == *term))
+                        .map(|term| format!("SPF record is missing {term}"))
+                        .collect();
+                    match missing.is_empty() {
+                        true => (Status::Ok, vec![]),
+                        false => (Status::Error, missing),
+                    }
+                }
+                _ => (
+                    Status::Error,
+                    vec![format!("multiple SPF records found for {}", record.name)],
+                ),
+            }
+        }
+        "TXT" => {
+            let expected = collapse_whitespace(&expected);
+            if actual.is_empty() {
+                not_found()
+            } else if actual
+                .iter()
+                .any(|value| collapse_whitespace(value) == expected)
+            {
+                (Status::Ok, vec![])
+            } else {
+                (
+                    Status::Error,
+                    vec![format!(
+                        "no TXT record for {} matches the expected value",
+                        record.name
+                    )],
+                )
+            }
+        }
+        _ => (
+            Status::Unknown,
+            vec![format!("cannot verify {record_type} records locally")],
+        ),
+    }
+}
+
+/// Returns the host of a CNAME or MX value, without the MX preference.
+fn target(value: &str) -> String {
+    normalize_name(value.split_whitespace().last().unwrap_or(value))
+}
+
+fn normalize_name(name: &str) -> String {
+    name.trim().trim_end_matches('.').to_lowercase()
+}
+
+fn collapse_whitespace(value: &str) -> String {
+    value.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Strips the HTML markup flowmailer uses in record value descriptions.
+fn plain_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            c if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    let text = text
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+    collapse_whitespace(&text)
+}
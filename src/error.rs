@@ -8,6 +8,7 @@ pub enum Kind {
     Parse(String),
     BadRequest(String),
     MissingHeader { header: String, msg: String },
+    Render(String),
     Other(String),
 }
 
@@ -32,6 +33,7 @@ impl Display for Error {
             Kind::Parse(msg) => format!("json: {msg}"),
             Kind::BadRequest(msg) => format!("bad request: {msg}"),
             Kind::MissingHeader { header, msg } => format!("missing header '{header}': {msg}"),
+            Kind::Render(msg) => format!("render: {msg}"),
             Kind::Other(msg) => msg.to_string(),
         })
     }
@@ -0,0 +1,741 @@
+//! Offline renderer for the commonly used subset of FreeMarker 2.3.20.
+//!
+//! Supported:
+//! - `${expr}` interpolation,
+//! - `<#if>`, `<#elseif>` and `<#else>`,
+//! - `<#list seq as item>` with the `item_index` and `item_has_next` loop variables,
+//! - `<#assign name = expr>` and `<#-- comments -->`,
+//! - variable paths (`order.lines[0].name`), string, number, boolean and sequence literals,
+//! - `!` defaults, `??` existence checks, `+`, comparisons, `&&`, `||` and `!`,
+//! - built-ins: `html`, `url`, `upper_case`, `lower_case`, `cap_first`, `uncap_first`, `trim`,
+//!   `length`, `size`, `string`, `c` and `has_content`.
+//!
+//! Syntax added in FreeMarker 2.3.23, such as `<#sep>`, `<#list>...<#else>` and the
+//! `?index`, `?counter` and `?has_next` loop variable built-ins, is rejected, since flowmailer
+//! does not support it. Numbers are printed as is, without the locale formatting FreeMarker
+//! applies.
+
+use crate::error::{self, Error, Result};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt::Display;
+
+/// Renders a FreeMarker template against the template data.
+pub fn render(source: &str, data: &Value) -> Result<String> {
+    let parts = split(source)?;
+    let mut pos = 0;
+    let (nodes, _) = parse_nodes(&parts, &mut pos, &[])?;
+    let mut scope = Scope {
+        data,
+        vars: vec![HashMap::new()],
+    };
+    let mut out = String::new();
+    render_nodes(&nodes, &mut scope, &mut out)?;
+    Ok(out)
+}
+
+fn render_error(msg: impl Into<String>) -> Error {
+    Error::new(error::Kind::Render(msg.into()))
+}
+
+/// Error for syntax flowmailer's FreeMarker 2.3.20 does not support yet.
+fn unsupported_in_2_3_20(syntax: &str, alternative: &str) -> Error {
+    render_error(format!(
+        "{syntax} requires FreeMarker 2.3.23, flowmailer uses 2.3.20; {alternative}"
+    ))
+}
+
+/// Raw template parts, before directives are nested.
+enum Part<'a> {
+    Text(&'a str),
+    Interpolation(&'a str),
+    /// Directive name and its parameters. Closing tags are named `/if`, `/list`, etc.
+    Directive(String, &'a str),
+}
+
+fn split(source: &str) -> Result<Vec<Part<'_>>> {
+    let mut parts = Vec::new();
+    let mut rest = source;
+    while !rest.is_empty() {
+        let next = ["${", "<#", "</#"]
+            .iter()
+            .filter_map(|tag| rest.find(tag))
+            .min();
+        let Some(start) = next else {
+            parts.push(Part::Text(rest));
+            break;
+        };
+        if start > 0 {
+            parts.push(Part::Text(&rest[..start]));
+        }
+        rest = &rest[start..];
+        if let Some(body) = rest.strip_prefix("${") {
+            let end =
+                find_end(body, '}').ok_or_else(|| render_error("unterminated ${ interpolation"))?;
+            parts.push(Part::Interpolation(&body[..end]));
+            rest = &body[end + 1..];
+        } else if let Some(body) = rest.strip_prefix("<#--") {
+            let end = body
+                .find("-->")
+                .ok_or_else(|| render_error("unterminated <#-- comment"))?;
+            rest = &body[end + 3..];
+        } else {
+            let closing = rest.starts_with("</#");
+            let body = &rest[if closing { 3 } else { 2 }..];
+            let name_len = body
+                .find(|c: char| !c.is_ascii_alphabetic())
+                .unwrap_or(body.len());
+            let end = find_end(body, '>').ok_or_else(|| render_error("unterminated directive"))?;
+            let name = &body[..name_len];
+            let params = body[name_len..end].trim().trim_end_matches('/').trim();
+            let name = if closing {
+                format!("/{name}")
+            } else {
+                name.to_string()
+            };
+            parts.push(Part::Directive(name, params));
+            rest = &body[end + 1..];
+        }
+    }
+    Ok(parts)
+}
+
+/// Finds `end` outside of string literals and parentheses.
+fn find_end(s: &str, end: char) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        match quote {
+            Some(_) if escaped => escaped = false,
+            Some(_) if c == '\\' => escaped = true,
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None => match c {
+                '"' | '\'' => quote = Some(c),
+                '(' | '[' | '{' => depth += 1,
+                ')' | ']' => depth = depth.saturating_sub(1),
+                '}' if depth > 0 => depth -= 1,
+                c if c == end && depth == 0 => return Some(i),
+                _ => {}
+            },
+        }
+    }
+    None
+}
+
+enum Node {
+    Text(String),
+    Interpolation(Expr),
+    If(Vec<(Expr, Vec<Node>)>, Vec<Node>),
+    List {
+        seq: Expr,
+        var: String,
+        body: Vec<Node>,
+    },
+    Assign(String, Expr),
+}
+
+/// Name and parameters of the directive that ended a block.
+type BlockEnd<'a> = Option<(String, &'a str)>;
+
+/// Parses nodes until one of the `ends` directives, which is returned with its parameters.
+fn parse_nodes<'a>(
+    parts: &[Part<'a>],
+    pos: &mut usize,
+    ends: &[&str],
+) -> Result<(Vec<Node>, BlockEnd<'a>)> {
+    let mut nodes = Vec::new();
+    while let Some(part) = parts.get(*pos) {
+        *pos += 1;
+        match part {
+            Part::Text(text) => nodes.push(Node::Text(text.to_string())),
+            Part::Interpolation(expr) => nodes.push(Node::Interpolation(parse_expr(expr)?)),
+            Part::Directive(name, params) if ends.contains(&name.as_str()) => {
+                return Ok((nodes, Some((name.clone(), params))));
+            }
+            Part::Directive(name, params) => nodes.push(parse_directive(name, params, parts, pos)?),
+        }
+    }
+    if ends.is_empty() {
+        Ok((nodes, None))
+    } else {
+        Err(render_error(format!("missing <{}>", ends.join("> or <"))))
+    }
+}
+
+fn parse_directive(name: &str, params: &str, parts: &[Part], pos: &mut usize) -> Result<Node> {
+    match name {
+        "if" => {
+            let mut branches = Vec::new();
+            let mut condition = parse_expr(params)?;
+            loop {
+                let (body, end) = parse_nodes(parts, pos, &["elseif", "else", "/if"])?;
+                branches.push((condition, body));
+                match end {
+                    Some((end, params)) if end == "elseif" => condition = parse_expr(params)?,
+                    Some((end, _)) if end == "else" => {
+                        let (otherwise, _) = parse_nodes(parts, pos, &["/if"])?;
+                        return Ok(Node::If(branches, otherwise));
+                    }
+                    _ => return Ok(Node::If(branches, Vec::new())),
+                }
+            }
+        }
+        "list" => {
+            let (seq, var) = params.rsplit_once(" as ").ok_or_else(|| {
+                render_error(format!("expected 'seq as item' in <#list {params}>"))
+            })?;
+            let (body, end) = parse_nodes(parts, pos, &["else", "/list"])?;
+            if matches!(&end, Some((end, _)) if end == "else") {
+                return Err(unsupported_in_2_3_20(
+                    "<#list> with <#else>",
+                    "wrap the list in <#if seq?has_content>",
+                ));
+            }
+            Ok(Node::List {
+                seq: parse_expr(seq)?,
+                var: var.trim().to_string(),
+                body,
+            })
+        }
+        "assign" => {
+            let (var, expr) = params.split_once('=').ok_or_else(|| {
+                render_error(format!("expected 'name = value' in <#assign {params}>"))
+            })?;
+            Ok(Node::Assign(var.trim().to_string(), parse_expr(expr)?))
+        }
+        "sep" => Err(unsupported_in_2_3_20(
+            "<#sep>",
+            "use <#if item_has_next> instead",
+        )),
+        name => Err(render_error(format!("unsupported directive <#{name}>"))),
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Num(f64),
+    Op(&'static str),
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>> {
+    const OPS: [&str; 19] = [
+        "??", "==", "!=", "<=", ">=", "&&", "||", "!", "?", ".", "(", ")", "[", "]", "<", ">", "+",
+        ",", "=",
+    ];
+    let mut tokens = Vec::new();
+    let mut rest = s.trim_start();
+    while let Some(c) = rest.chars().next() {
+        if c == '"' || c == '\'' {
+            let mut value = String::new();
+            let mut chars = rest[1..].char_indices();
+            let end = loop {
+                match chars.next() {
+                    Some((i, q)) if q == c => break i + 2,
+                    Some((_, '\\')) => {
+                        if let Some((_, escaped)) = chars.next() {
+                            value.push(match escaped {
+                                'n' => '\n',
+                                't' => '\t',
+                                other => other,
+                            });
+                        }
+                    }
+                    Some((_, other)) => value.push(other),
+                    None => return Err(render_error(format!("unterminated string in '{s}'"))),
+                }
+            };
+            tokens.push(Token::Str(value));
+            rest = &rest[end..];
+        } else if c.is_ascii_digit() {
+            let end = rest
+                .find(|c: char| !c.is_ascii_digit() && c != '.')
+                .unwrap_or(rest.len());
+            let number = rest[..end].trim_end_matches('.');
+            let value = number
+                .parse()
+                .map_err(|_| render_error(format!("invalid number '{number}'")))?;
+            tokens.push(Token::Num(value));
+            rest = &rest[number.len()..];
+        } else if c.is_alphabetic() || c == '_' {
+            let end = rest
+                .find(|c: char| !c.is_alphanumeric() && c != '_')
+                .unwrap_or(rest.len());
+            let word = &rest[..end];
+            tokens.push(match word {
+                "gt" => Token::Op(">"),
+                "gte" => Token::Op(">="),
+                "lt" => Token::Op("<"),
+                "lte" => Token::Op("<="),
+                word => Token::Ident(word.to_string()),
+            });
+            rest = &rest[end..];
+        } else {
+            let op = OPS
+                .iter()
+                .find(|op| rest.starts_with(**op))
+                .ok_or_else(|| render_error(format!("unexpected '{c}' in '{s}'")))?;
+            rest = &rest[op.len()..];
+            // A single `=` is a comparison too, like in FreeMarker.
+            tokens.push(Token::Op(if *op == "=" { "==" } else { op }));
+        }
+        rest = rest.trim_start();
+    }
+    Ok(tokens)
+}
+
+enum Expr {
+    Literal(Value),
+    Sequence(Vec<Expr>),
+    Var(String),
+    Member(Box<Expr>, Box<Expr>),
+    Builtin(Box<Expr>, String, Vec<Expr>),
+    Default(Box<Expr>, Option<Box<Expr>>),
+    Exists(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(&'static str, Box<Expr>, Box<Expr>),
+}
+
+impl Display for Expr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Literal(value) => write!(f, "{value}"),
+            Self::Sequence(items) => {
+                let items: Vec<_> = items.iter().map(ToString::to_string).collect();
+                write!(f, "[{}]", items.join(", "))
+            }
+            Self::Var(name) => f.write_str(name),
+            Self::Member(target, key) => match &**key {
+                Self::Literal(Value::String(key)) => write!(f, "{target}.{key}"),
+                key => write!(f, "{target}[{key}]"),
+            },
+            Self::Builtin(target, name, _) => write!(f, "{target}?{name}"),
+            Self::Default(expr, Some(default)) => write!(f, "{expr}!{default}"),
+            Self::Default(expr, None) => write!(f, "{expr}!"),
+            Self::Exists(expr) => write!(f, "{expr}??"),
+            Self::Not(expr) => write!(f, "!{expr}"),
+            Self::Binary(op, left, right) => write!(f, "{left} {op} {right}"),
+        }
+    }
+}
+
+fn parse_expr(s: &str) -> Result<Expr> {
+    let tokens = tokenize(s)?;
+    let mut parser = Parser { tokens, pos: 0 };
+    let expr = parser.or()?;
+    match parser.tokens.get(parser.pos) {
+        None => Ok(expr),
+        Some(token) => Err(render_error(format!("unexpected {token:?} in '{s}'"))),
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn eat(&mut self, op: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Op(current)) if *current == op) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, op: &str) -> Result<()> {
+        if self.eat(op) {
+            Ok(())
+        } else {
+            Err(render_error(format!("expected '{op}'")))
+        }
+    }
+
+    fn binary(
+        &mut self,
+        ops: &[&'static str],
+        next: fn(&mut Self) -> Result<Expr>,
+    ) -> Result<Expr> {
+        let mut left = next(self)?;
+        while let Some(&op) = ops.iter().find(|op| self.peek() == Some(&Token::Op(op))) {
+            self.pos += 1;
+            left = Expr::Binary(op, Box::new(left), Box::new(next(self)?));
+        }
+        Ok(left)
+    }
+
+    fn or(&mut self) -> Result<Expr> {
+        self.binary(&["||"], Self::and)
+    }
+
+    fn and(&mut self) -> Result<Expr> {
+        self.binary(&["&&"], Self::comparison)
+    }
+
+    fn comparison(&mut self) -> Result<Expr> {
+        self.binary(&["==", "!=", "<=", ">=", "<", ">"], Self::sum)
+    }
+
+    fn sum(&mut self) -> Result<Expr> {
+        self.binary(&["+"], Self::unary)
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        if self.eat("!") {
+            Ok(Expr::Not(Box::new(self.unary()?)))
+        } else {
+            self.postfix()
+        }
+    }
+
+    fn postfix(&mut self) -> Result<Expr> {
+        let mut expr = self.primary()?;
+        loop {
+            if self.eat(".") {
+                let name = self.ident()?;
+                expr = Expr::Member(Box::new(expr), Box::new(Expr::Literal(Value::String(name))));
+            } else if self.eat("[") {
+                let index = self.or()?;
+                self.expect("]")?;
+                expr = Expr::Member(Box::new(expr), Box::new(index));
+            } else if self.eat("??") {
+                expr = Expr::Exists(Box::new(expr));
+            } else if self.eat("?") {
+                let name = self.ident()?;
+                let mut args = Vec::new();
+                if self.eat("(") {
+                    while !self.eat(")") {
+                        args.push(self.or()?);
+                        self.eat(",");
+                    }
+                }
+                expr = Expr::Builtin(Box::new(expr), name, args);
+            } else if self.eat("!") {
+                let default = match self.peek() {
+                    None | Some(Token::Op(")" | "]" | "," | "==" | "!=" | "&&" | "||")) => None,
+                    _ => Some(Box::new(self.unary()?)),
+                };
+                expr = Expr::Default(Box::new(expr), default);
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr> {
+        let token = self.peek().cloned();
+        self.pos += 1;
+        match token {
+            Some(Token::Str(value)) => Ok(Expr::Literal(Value::String(value))),
+            Some(Token::Num(value)) => Ok(Expr::Literal(number(value))),
+            Some(Token::Ident(word)) if word == "true" => Ok(Expr::Literal(Value::Bool(true))),
+            Some(Token::Ident(word)) if word == "false" => Ok(Expr::Literal(Value::Bool(false))),
+            Some(Token::Ident(word)) => Ok(Expr::Var(word)),
+            Some(Token::Op("(")) => {
+                let expr = self.or()?;
+                self.expect(")")?;
+                Ok(expr)
+            }
+            Some(Token::Op("[")) => {
+                let mut items = Vec::new();
+                while !self.eat("]") {
+                    items.push(self.or()?);
+                    if !self.eat(",") {
+                        self.expect("]")?;
+                        break;
+                    }
+                }
+                Ok(Expr::Sequence(items))
+            }
+            Some(token) => Err(render_error(format!("unexpected {token:?}"))),
+            None => Err(render_error("unexpected end of expression")),
+        }
+    }
+
+    fn ident(&mut self) -> Result<String> {
+        match self.peek().cloned() {
+            Some(Token::Ident(name)) => {
+                self.pos += 1;
+                Ok(name)
+            }
+            token => Err(render_error(format!("expected a name, found {token:?}"))),
+        }
+    }
+}
+
+fn number(value: f64) -> Value {
+    if value.fract() == 0.0 && value.abs() < i64::MAX as f64 {
+        Value::from(value as i64)
+    } else {
+        Value::from(value)
+    }
+}
+
+struct Scope<'a> {
+    data: &'a Value,
+    vars: Vec<HashMap<String, Value>>,
+}
+
+impl Scope<'_> {
+    fn lookup(&self, name: &str) -> Option<Value> {
+        self.vars
+            .iter()
+            .rev()
+            .find_map(|vars| vars.get(name))
+            .or_else(|| self.data.get(name))
+            .cloned()
+    }
+}
+
+fn render_nodes(nodes: &[Node], scope: &mut Scope, out: &mut String) -> Result<()> {
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Interpolation(expr) => {
+                let value = eval(expr, scope)?
+                    .ok_or_else(|| render_error(format!("'{expr}' is missing")))?;
+                out.push_str(&display(&value)?);
+            }
+            Node::If(branches, otherwise) => {
+                let mut body = otherwise;
+                for (condition, branch) in branches {
+                    if truthy(condition, scope)? {
+                        body = branch;
+                        break;
+                    }
+                }
+                render_nodes(body, scope, out)?;
+            }
+            Node::List { seq, var, body } => {
+                let items = match eval(seq, scope)? {
+                    Some(Value::Array(items)) => items,
+                    Some(Value::Null) | None => {
+                        return Err(render_error(format!("'{seq}' is missing")))
+                    }
+                    Some(other) => return Err(render_error(format!("cannot list {other}"))),
+                };
+                let len = items.len();
+                for (index, item) in items.into_iter().enumerate() {
+                    scope.vars.push(HashMap::from([
+                        (var.clone(), item),
+                        (format!("{var}_index"), Value::from(index)),
+                        (format!("{var}_has_next"), Value::Bool(index + 1 < len)),
+                    ]));
+                    render_nodes(body, scope, out)?;
+                    scope.vars.pop();
+                }
+            }
+            Node::Assign(var, expr) => {
+                let value = eval(expr, scope)?
+                    .ok_or_else(|| render_error(format!("'{expr}' is missing")))?;
+                if let Some(vars) = scope.vars.first_mut() {
+                    vars.insert(var.clone(), value);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn truthy(expr: &Expr, scope: &Scope) -> Result<bool> {
+    match eval(expr, scope)? {
+        Some(Value::Bool(value)) => Ok(value),
+        Some(Value::Null) | None => Err(render_error(format!("'{expr}' is missing"))),
+        Some(other) => Err(render_error(format!("condition {other} is not a boolean"))),
+    }
+}
+
+fn display(value: &Value) -> Result<String> {
+    match value {
+        Value::String(value) => Ok(value.clone()),
+        Value::Number(value) => Ok(value.to_string()),
+        Value::Bool(_) => Err(render_error(
+            "cannot print a boolean, use ?c or ?string(\"yes\", \"no\")",
+        )),
+        other => Err(render_error(format!("cannot print {other}"))),
+    }
+}
+
+/// Evaluates an expression, returning None for missing values.
+fn eval(expr: &Expr, scope: &Scope) -> Result<Option<Value>> {
+    let value = match expr {
+        Expr::Literal(value) => Some(value.clone()),
+        Expr::Sequence(items) => {
+            let mut values = Vec::with_capacity(items.len());
+            for item in items {
+                values.push(eval(item, scope)?.unwrap_or(Value::Null));
+            }
+            Some(Value::Array(values))
+        }
+        Expr::Var(name) => scope.lookup(name),
+        Expr::Member(target, key) => {
+            let target = eval(target, scope)?;
+            let key = eval(key, scope)?;
+            match (target, key) {
+                (Some(target), Some(Value::String(key))) => target.get(&key).cloned(),
+                (Some(target), Some(Value::Number(index))) => index
+                    .as_u64()
+                    .and_then(|index| target.get(index as usize))
+                    .cloned(),
+                _ => None,
+            }
+        }
+        Expr::Default(expr, default) => match eval(expr, scope)? {
+            Some(Value::Null) | None => match default {
+                Some(default) => eval(default, scope)?,
+                None => Some(Value::String(String::new())),
+            },
+            value => value,
+        },
+        Expr::Exists(expr) => Some(Value::Bool(!matches!(
+            eval(expr, scope)?,
+            Some(Value::Null) | None
+        ))),
+        Expr::Not(expr) => Some(Value::Bool(!truthy(expr, scope)?)),
+        Expr::Binary("&&", left, right) => {
+            Some(Value::Bool(truthy(left, scope)? && truthy(right, scope)?))
+        }
+        Expr::Binary("||", left, right) => {
+            Some(Value::Bool(truthy(left, scope)? || truthy(right, scope)?))
+        }
+        Expr::Binary(op, left, right) => {
+            let missing = |expr: &Expr| render_error(format!("'{expr}' is missing"));
+            let left = eval(left, scope)?.ok_or_else(|| missing(left))?;
+            let right = eval(right, scope)?.ok_or_else(|| missing(right))?;
+            Some(binary(op, &left, &right)?)
+        }
+        Expr::Builtin(target, name, args) => builtin(target, name, args, scope)?,
+    };
+    Ok(value.filter(|value| !value.is_null()))
+}
+
+fn binary(op: &str, left: &Value, right: &Value) -> Result<Value> {
+    use std::cmp::Ordering;
+    if op == "+" {
+        return Ok(match (left, right) {
+            (Value::Number(l), Value::Number(r)) => {
+                number(l.as_f64().unwrap_or(0.0) + r.as_f64().unwrap_or(0.0))
+            }
+            (left, right) => Value::String(display(left)? + &display(right)?),
+        });
+    }
+    let ordering = match (left, right) {
+        (Value::Number(l), Value::Number(r)) => l.as_f64().partial_cmp(&r.as_f64()),
+        (Value::String(l), Value::String(r)) => Some(l.cmp(r)),
+        (Value::Bool(l), Value::Bool(r)) if matches!(op, "==" | "!=") => Some(l.cmp(r)),
+        (left, right) => {
+            return Err(render_error(format!("cannot compare {left} with {right}")));
+        }
+    };
+    let ordering = ordering.ok_or_else(|| render_error("cannot compare NaN"))?;
+    Ok(Value::Bool(match op {
+        "==" => ordering == Ordering::Equal,
+        "!=" => ordering != Ordering::Equal,
+        "<" => ordering == Ordering::Less,
+        "<=" => ordering != Ordering::Greater,
+        ">" => ordering == Ordering::Greater,
+        _ => ordering != Ordering::Less,
+    }))
+}
+
+fn builtin(target: &Expr, name: &str, args: &[Expr], scope: &Scope) -> Result<Option<Value>> {
+    if matches!(name, "index" | "counter" | "has_next") {
+        let alternative = match name {
+            "has_next" => format!("use {target}_has_next instead"),
+            _ => format!("use {target}_index instead"),
+        };
+        return Err(unsupported_in_2_3_20(&format!("?{name}"), &alternative));
+    }
+    let value = eval(target, scope)?;
+    if name == "has_content" {
+        return Ok(Some(Value::Bool(match &value {
+            None => false,
+            Some(Value::String(s)) => !s.is_empty(),
+            Some(Value::Array(items)) => !items.is_empty(),
+            Some(Value::Object(map)) => !map.is_empty(),
+            Some(_) => true,
+        })));
+    }
+    let value = value.ok_or_else(|| render_error(format!("'{target}' is missing")))?;
+    let string = |f: fn(&str) -> String| -> Result<Option<Value>> {
+        Ok(Some(Value::String(f(&display(&value)?))))
+    };
+    match (name, &value) {
+        ("html", _) => string(escape_html),
+        ("url", _) => string(escape_url),
+        ("upper_case", _) => string(str::to_uppercase),
+        ("lower_case", _) => string(str::to_lowercase),
+        ("trim", _) => string(|s| s.trim().to_string()),
+        ("cap_first", _) => string(|s| map_first(s, char::to_uppercase)),
+        ("uncap_first", _) => string(|s| map_first(s, char::to_lowercase)),
+        ("length", Value::String(s)) => Ok(Some(Value::from(s.chars().count()))),
+        ("size", Value::Array(items)) => Ok(Some(Value::from(items.len()))),
+        ("size", Value::Object(map)) => Ok(Some(Value::from(map.len()))),
+        ("c" | "string", Value::Bool(value)) if args.is_empty() => {
+            Ok(Some(Value::String(value.to_string())))
+        }
+        ("string", Value::Bool(value)) => {
+            let arg = args
+                .get(if *value { 0 } else { 1 })
+                .ok_or_else(|| render_error("?string needs two arguments for booleans"))?;
+            eval(arg, scope)
+        }
+        ("c" | "string", _) => string(str::to_string),
+        (name, value) => Err(render_error(format!(
+            "unsupported built-in ?{name} for {value}"
+        ))),
+    }
+}
+
+fn map_first<I: Iterator<Item = char>>(s: &str, f: fn(char) -> I) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(first) => f(first).chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn escape_html(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Percent-encodes everything except letters, digits and `_-.!~*'()`, like `?url` does.
+fn escape_url(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for byte in s.bytes() {
+        match byte {
+            b'a'..=b'z'
+            | b'A'..=b'Z'
+            | b'0'..=b'9'
+            | b'_'
+            | b'-'
+            | b'.'
+            | b'!'
+            | b'~'
+            | b'*'
+            | b'\''
+            | b'('
+            | b')' => escaped.push(byte as char),
+            byte => escaped.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    escaped
+}
@@ -1,13 +1,20 @@
-pub extern crate reqwest;
-
 pub mod auth;
+#[cfg(feature = "blocking")]
+pub mod blocking;
 pub mod client;
+pub mod dns;
 pub mod error;
+pub mod freemarker;
 pub mod mail;
+pub mod outbox;
 pub mod request;
 pub mod resources;
 pub mod rest_api;
+pub mod sync;
 pub mod template;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod transport;
 pub mod ty;
 
 #[cfg(test)]
@@ -18,4 +25,4 @@ pub use client::Client;
 pub use error::Result;
 pub use mail::{MailAddress, MailBuilder};
 pub use resources::{Attachment, AttachmentBuilder};
-pub use template::Template;
+pub use template::{Template, TypedTemplate};
@@ -1,7 +1,9 @@
 use serde::Serialize;
 
-use crate::error::{self, Error, Result};
+use crate::error::Result;
+use crate::outbox::DeliveryMode;
 use crate::resources::{Attachment, SubmitMessage};
+use crate::template::TypedTemplate;
 use crate::{rest_api, Client};
 
 pub struct MailAddress(String);
@@ -15,6 +17,7 @@ pub struct MailBuilder {
     flow_selector: Option<String>,
     mail_data: Option<serde_json::Value>,
     attachments: Vec<Attachment>,
+    source_id: Option<String>,
 }
 
 impl MailAddress {
@@ -34,6 +37,7 @@ impl MailBuilder {
             flow_selector: None,
             mail_data: None,
             attachments: Vec::new(),
+            source_id: None,
         }
     }
 
@@ -59,11 +63,27 @@ impl MailBuilder {
         new
     }
 
+    /// Creates a template mail whose flow selector is determined by the type of `data`.
+    pub fn new_typed<T: TypedTemplate>(
+        sender: MailAddress,
+        recipient: MailAddress,
+        data: T,
+    ) -> Result<Self> {
+        Self::new_template(sender, recipient, T::FLOW_SELECTOR).set_data(data)
+    }
+
     pub fn set_data(mut self, data: impl Serialize) -> Result<Self> {
         self.mail_data = Some(serde_json::to_value(data)?);
         Ok(self)
     }
 
+    /// Submits the mail through the API source `source_id`, whose credentials must be
+    /// registered with [`Client::with_source`].
+    pub fn set_source_id(mut self, source_id: impl Into<String>) -> Self {
+        self.source_id = Some(source_id.into());
+        self
+    }
+
     pub fn set_subject(mut self, subject: impl Into<String>) -> Self {
         self.subject = subject.into();
         self
@@ -99,52 +119,53 @@ impl MailBuilder {
         self
     }
 
-    pub async fn send(self, client: &Client) -> Result<()> {
+    /// Sends the mail with a blocking client, see [`MailBuilder::send`].
+    #[cfg(feature = "blocking")]
+    pub fn send_blocking(self, client: &crate::blocking::Client) -> Result<String> {
+        client.run(|client| self.send(client))
+    }
+
+    /// Submits the mail and returns its message ID, or captures it when the client is in
+    /// [`DeliveryMode::Capture`] and returns a synthetic ID.
+    pub async fn send(self, client: &Client) -> Result<String> {
         let attachments = if self.attachments.is_empty() {
             None
         } else {
             Some(self.attachments.into_boxed_slice())
         };
 
-        let response = rest_api::message::submit(
-            client,
-            SubmitMessage {
-                data: self.mail_data.unwrap_or(serde_json::json!({})),
-                header_from_address: self.sender.0.clone(),
-                header_from_name: "".to_string(),
-                header_to_name: "".to_string(),
-                headers: Box::new([]),
-                message_type: crate::ty::MessageType::Email,
-                recipient_address: self.recipient.0,
-                sender_address: self.sender.0,
-                subject: self.subject,
-                text: self.text,
-                attachments,
-                delivery_notification_type: None,
-                flow_selector: self.flow_selector,
-                header_to_address: None,
-                html: self.html,
-                mimedata: None,
-                schedule_at: None,
-                tags: None,
-            },
-        )
-        .await?
-        .into_inner();
-        if let Some(location) = response.headers().get("location") {
-            if let Ok(_content) = location.to_str() {
-                // parse url and key ID
-                Ok(())
-            } else {
-                Err(Error::new(error::Kind::Parse(format!(
-                    "failed to interpret header content as string for '{location:?}'"
-                ))))
+        let message = SubmitMessage {
+            data: self.mail_data.unwrap_or(serde_json::json!({})),
+            header_from_address: self.sender.0.clone(),
+            header_from_name: "".to_string(),
+            header_to_name: "".to_string(),
+            headers: Box::new([]),
+            message_type: crate::ty::MessageType::Email,
+            recipient_address: self.recipient.0,
+            sender_address: self.sender.0,
+            subject: self.subject,
+            text: self.text,
+            attachments,
+            delivery_notification_type: None,
+            flow_selector: self.flow_selector,
+            header_to_address: None,
+            html: self.html,
+            mimedata: None,
+            schedule_at: None,
+            tags: None,
+        };
+        let response = match (client.delivery(), &self.source_id) {
+            (DeliveryMode::Capture(outbox), source_id) => {
+                let account_id = client.account_id().as_str();
+                return outbox
+                    .capture(account_id, source_id.as_deref(), message)
+                    .await;
             }
-        } else {
-            Err(Error::new(error::Kind::MissingHeader {
-                header: "location".to_string(),
-                msg: "need location header in order to extract ID".to_string(),
-            }))
-        }
+            (DeliveryMode::Send, Some(source_id)) => {
+                rest_api::message::submit_from(client, source_id, message).await?
+            }
+            (DeliveryMode::Send, None) => rest_api::message::submit(client, message).await?,
+        };
+        response.location_id()
     }
 }
@@ -0,0 +1,219 @@
+//! Capturing mail instead of sending it, for staging and local development.
+//!
+//! A client in [`DeliveryMode::Capture`] stores every message sent with
+//! [`MailBuilder::send`](crate::MailBuilder::send) in an [`Outbox`] and returns a synthetic
+//! message ID, without calling flowmailer.
+//!
+//! # Example
+//! ```no_run
+//! use flowmailer::outbox::{DeliveryMode, Outbox};
+//! use flowmailer::{Auth, Client, MailAddress, MailBuilder};
+//!
+//! # async fn example() -> flowmailer::Result<()> {
+//! let outbox = Outbox::new();
+//! let client = Client::new(Auth::from_env()?).delivery_mode(DeliveryMode::Capture(outbox.clone()));
+//! MailBuilder::new_text(
+//!     MailAddress::new("sender@example.com"),
+//!     MailAddress::new("recipient@example.com"),
+//!     "Hello!",
+//! )
+//! .send(&client)
+//! .await?;
+//! let captured = outbox.wait_for(1).await;
+//! assert_eq!(captured[0].message.recipient_address, "recipient@example.com");
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{self, Error, Result};
+use crate::resources::SubmitMessage;
+use crate::ty;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Poll, Waker};
+
+/// How a client delivers mail sent with [`MailBuilder::send`](crate::MailBuilder::send).
+#[derive(Clone, Default)]
+pub enum DeliveryMode {
+    /// Submit messages to flowmailer.
+    #[default]
+    Send,
+    /// Store messages in an outbox instead of submitting them.
+    Capture(Outbox),
+}
+
+/// A message stored by [`DeliveryMode::Capture`].
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CapturedMessage {
+    /// Synthetic message ID, returned by `MailBuilder::send`.
+    pub id: String,
+    /// Account the message would have been submitted to.
+    pub account_id: String,
+    /// Source the message would have been submitted through.
+    pub source_id: Option<String>,
+    pub captured: ty::Date,
+    pub message: SubmitMessage,
+}
+
+/// In-memory store of captured messages, optionally also written to a directory.
+///
+/// Clones share the same messages.
+#[derive(Clone, Default)]
+pub struct Outbox {
+    inner: Arc<Mutex<Inner>>,
+}
+
+#[derive(Default)]
+struct Inner {
+    dir: Option<PathBuf>,
+    messages: Vec<CapturedMessage>,
+    next_id: u64,
+    waiting: Vec<Waker>,
+}
+
+impl Outbox {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an outbox that also writes each message as `<id>.json` to `dir`,
+    /// so it can be inspected from outside the process.
+    ///
+    /// IDs continue after the messages already in `dir`, so files from an earlier run are
+    /// not overwritten. With the `blocking` or `testing` feature, files are written on
+    /// tokio's blocking thread pool when sending from a tokio runtime; otherwise they are
+    /// written synchronously, blocking the sending task while the message is written.
+    pub fn on_disk(dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir).map_err(|err| disk_error(&dir, err))?;
+        let mut last_id = 0;
+        for entry in std::fs::read_dir(&dir).map_err(|err| disk_error(&dir, err))? {
+            let entry = entry.map_err(|err| disk_error(&dir, err))?;
+            let id = entry
+                .file_name()
+                .to_str()
+                .and_then(|name| name.strip_prefix("captured"))
+                .and_then(|name| name.strip_suffix(".json"))
+                .and_then(|id| id.parse().ok());
+            last_id = last_id.max(id.unwrap_or(0));
+        }
+        let outbox = Self::new();
+        let mut inner = outbox.lock();
+        inner.dir = Some(dir);
+        inner.next_id = last_id;
+        drop(inner);
+        Ok(outbox)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Stores `message` and returns its synthetic ID.
+    pub(crate) async fn capture(
+        &self,
+        account_id: &str,
+        source_id: Option<&str>,
+        message: SubmitMessage,
+    ) -> Result<String> {
+        let (id, dir) = {
+            let mut inner = self.lock();
+            inner.next_id += 1;
+            (format!("captured{:016}", inner.next_id), inner.dir.clone())
+        };
+        let captured = CapturedMessage {
+            id: id.clone(),
+            account_id: account_id.to_string(),
+            source_id: source_id.map(str::to_string),
+            captured: ty::Date::now(),
+            message,
+        };
+        if let Some(dir) = dir {
+            let path = dir.join(format!("{id}.json"));
+            let json = serde_json::to_vec_pretty(&captured)?;
+            write_file(path, json).await?;
+        }
+        let mut inner = self.lock();
+        inner.messages.push(captured);
+        inner.waiting.drain(..).for_each(Waker::wake);
+        Ok(id)
+    }
+
+    /// Returns the captured messages, oldest first.
+    pub fn list(&self) -> Vec<CapturedMessage> {
+        self.lock().messages.clone()
+    }
+
+    /// Returns the captured message with `id`.
+    pub fn get(&self, id: &str) -> Option<CapturedMessage> {
+        let inner = self.lock();
+        inner
+            .messages
+            .iter()
+            .find(|captured| captured.id == id)
+            .cloned()
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().messages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Removes all captured messages, including the files written for them.
+    pub fn clear(&self) -> Result<()> {
+        let mut inner = self.lock();
+        if let Some(dir) = &inner.dir {
+            for captured in &inner.messages {
+                let path = dir.join(format!("{}.json", captured.id));
+                match std::fs::remove_file(&path) {
+                    Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                        return Err(disk_error(&path, err))
+                    }
+                    _ => {}
+                }
+            }
+        }
+        inner.messages.clear();
+        Ok(())
+    }
+
+    /// Waits until at least `count` messages are captured and returns all captured messages.
+    /// Wrap in a timeout to avoid waiting forever in tests.
+    pub async fn wait_for(&self, count: usize) -> Vec<CapturedMessage> {
+        std::future::poll_fn(|cx| {
+            let mut inner = self.lock();
+            if inner.messages.len() >= count {
+                Poll::Ready(inner.messages.clone())
+            } else {
+                inner.waiting.push(cx.waker().clone());
+                Poll::Pending
+            }
+        })
+        .await
+    }
+}
+
+/// Writes a captured message, off the async task when running on a tokio runtime.
+async fn write_file(path: PathBuf, contents: Vec<u8>) -> Result<()> {
+    let write = move || std::fs::write(&path, contents).map_err(|err| disk_error(&path, err));
+    #[cfg(any(feature = "blocking", feature = "testing"))]
+    if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+        return runtime
+            .spawn_blocking(write)
+            .await
+            .map_err(|err| Error::new(error::Kind::Other(format!("outbox: {err}"))))?;
+    }
+    write()
+}
+
+fn disk_error(path: &std::path::Path, err: std::io::Error) -> Error {
+    Error::new(error::Kind::Other(format!(
+        "outbox: '{}': {err}",
+        path.display()
+    )))
+}
@@ -1,111 +1,222 @@
 use crate::error::{self, Error};
-use crate::{Client, Result};
-use reqwest::{Body, Url};
+use crate::transport::{HttpRequest, HttpResponse, Method};
+use crate::{ty, Client, Result};
+use serde::de::DeserializeOwned;
 use serde::Serialize;
 
 pub struct RequestBuilder<'a> {
-    inner: reqwest::RequestBuilder,
+    request: Result<HttpRequest>,
     client: &'a Client,
+    source_id: Option<&'a str>,
 }
 
 #[derive(Debug)]
-pub struct Response(reqwest::Response);
+pub struct Response(HttpResponse);
 
 impl Response {
-    pub(crate) fn new(response: reqwest::Response) -> Self {
+    pub(crate) fn new(response: HttpResponse) -> Self {
         Self(response)
     }
 
-    pub(crate) fn into_inner(self) -> reqwest::Response {
+    /// Returns the raw response.
+    pub fn into_inner(self) -> HttpResponse {
         self.0
     }
+
+    /// Returns the HTTP status code.
+    pub fn status(&self) -> u16 {
+        self.0.status
+    }
+
+    /// Returns the value of a response header, if present.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.0.header_value(name)
+    }
+
+    /// Parses the `Content-Range` header of a paginated list response.
+    pub fn content_range(&self) -> Result<Option<ty::ContentRange>> {
+        self.header("content-range").map(str::parse).transpose()
+    }
+
+    /// Returns the ID of a created resource from the last segment of the `Location` header.
+    pub fn location_id(&self) -> Result<String> {
+        let location = self.header("location").ok_or_else(|| {
+            Error::new(error::Kind::MissingHeader {
+                header: "location".to_string(),
+                msg: "need location header in order to extract ID".to_string(),
+            })
+        })?;
+        match location.trim_end_matches('/').rsplit('/').next() {
+            Some(id) if !id.is_empty() => Ok(id.to_string()),
+            _ => Err(Error::new(error::Kind::Parse(format!(
+                "no ID in location header '{location}'"
+            )))),
+        }
+    }
+
+    /// Deserializes the response body as json.
+    pub async fn json<T: DeserializeOwned>(self) -> Result<T> {
+        Ok(serde_json::from_slice(&self.0.body)?)
+    }
 }
 
 impl<'a> RequestBuilder<'a> {
-    const BASE_URL: &'static str = "https://api.flowmailer.net";
+    const MEDIA_TYPE: &'static str = "application/vnd.flowmailer.v1.12+json;charset=UTF-8";
+
+    fn new(
+        client: &'a Client,
+        method: Method,
+        path: &str,
+        matrix_args: &[(&str, &str)],
+    ) -> Result<Self> {
+        let url = Self::create_url(client, path, matrix_args)?;
+        let request = HttpRequest {
+            method,
+            url,
+            headers: Vec::new(),
+            body: None,
+        };
+        Ok(Self {
+            request: Ok(request),
+            client,
+            source_id: None,
+        })
+    }
 
     pub fn post(client: &'a Client, path: &str, matrix_args: &[(&str, &str)]) -> Result<Self> {
-        let url = Self::create_url(client, &path, matrix_args)?;
-        let inner = client.reqw_client().post(url);
-        Ok(Self { client, inner })
+        Self::new(client, Method::Post, path, matrix_args)
     }
 
     pub fn get(client: &'a Client, path: &str, matrix_args: &[(&str, &str)]) -> Result<Self> {
-        let url = Self::create_url(client, path, matrix_args)?;
-        let inner = client.reqw_client().get(url);
-        Ok(Self { client, inner })
+        Self::new(client, Method::Get, path, matrix_args)
     }
 
-    pub fn body(mut self, val: impl Into<Body>) -> Self {
-        self.inner = self.inner.body(val);
-        self
+    pub fn put(client: &'a Client, path: &str, matrix_args: &[(&str, &str)]) -> Result<Self> {
+        Self::new(client, Method::Put, path, matrix_args)
     }
 
-    pub fn query(mut self, val: &impl Serialize) -> Self {
-        self.inner = self.inner.query(val);
-        self
+    pub fn delete(client: &'a Client, path: &str, matrix_args: &[(&str, &str)]) -> Result<Self> {
+        Self::new(client, Method::Delete, path, matrix_args)
     }
 
-    pub fn form(mut self, val: &impl Serialize) -> Self {
-        self.inner = self.inner.form(val);
+    fn map(mut self, f: impl FnOnce(&mut HttpRequest) -> Result<()>) -> Self {
+        self.request = self
+            .request
+            .and_then(|mut request| f(&mut request).map(|()| request));
         self
     }
 
+    pub fn body(self, val: impl Into<Vec<u8>>) -> Self {
+        self.map(|request| {
+            request.body = Some(val.into());
+            Ok(())
+        })
+    }
+
+    /// Appends `val`, serialized as url encoded pairs, to the query string.
+    pub fn query(self, val: &impl Serialize) -> Self {
+        self.map(|request| {
+            let query = serde_urlencoded::to_string(val)
+                .map_err(|err| Error::new(error::Kind::Parse(err.to_string())))?;
+            if !query.is_empty() {
+                let separator = if request.url.contains('?') { '&' } else { '?' };
+                request.url = format!("{}{separator}{query}", request.url);
+            }
+            Ok(())
+        })
+    }
+
+    pub fn header(self, name: &str, val: &str) -> Self {
+        self.map(|request| {
+            request.headers.push((name.to_string(), val.to_string()));
+            Ok(())
+        })
+    }
+
+    /// Limits a list call to the given items.
+    pub fn range(self, range: &ty::ItemsRange) -> Self {
+        self.header("Range", &format!("items={}-{}", range.start, range.end))
+    }
+
+    /// Authenticates with the credentials registered for `source_id` instead of the
+    /// client's own credentials.
+    pub fn source(mut self, source_id: &'a str) -> Result<Self> {
+        if !self.client.has_source(source_id) {
+            return Err(Error::new(error::Kind::Auth(format!(
+                "no credentials registered for source '{source_id}'"
+            ))));
+        }
+        self.source_id = Some(source_id);
+        Ok(self)
+    }
+
+    /// Sets `val`, serialized as url encoded form, as body.
+    pub fn form(self, val: &impl Serialize) -> Self {
+        self.map(|request| {
+            let form = serde_urlencoded::to_string(val)
+                .map_err(|err| Error::new(error::Kind::Parse(err.to_string())))?;
+            request.body = Some(form.into_bytes());
+            Ok(())
+        })
+        .header("Content-Type", "application/x-www-form-urlencoded")
+    }
+
     pub async fn execute(self) -> Result<Response> {
-        let auth = self.client.authenticate();
+        let token = self.client.authenticate(self.source_id).await?;
         let request = self
-            .inner
-            .bearer_auth(auth.await?)
-            .header(
-                "Accept",
-                "application/vnd.flowmailer.v1.12+json;charset=UTF-8",
-            )
-            .header("Host", "api.flowmailer.net")
             .header(
-                "Content-Type",
-                "application/vnd.flowmailer.v1.12+json;charset=UTF-8",
+                "Authorization",
+                &format!("Bearer {}", token.expose_secret()),
             )
-            .build()?;
-        let response = self.client.reqw_client().execute(request).await;
-        match response {
-            Ok(response) => {
-                if response.status().is_success() {
-                    Ok(Response::new(response))
-                } else {
-                    Err(Error::new(error::Kind::BadRequest(
-                        response.text().await.unwrap_or("no text body".to_string()),
-                    )))
-                }
-            }
-            Err(error)
-                if error.is_status()
-                    && error.status().expect("expected status error")
-                        == reqwest::StatusCode::UNAUTHORIZED =>
-            {
+            .header("Accept", Self::MEDIA_TYPE)
+            .header("Content-Type", Self::MEDIA_TYPE);
+        let response = request.client.http().send(request.request?).await?;
+        match response.status {
+            401 => {
+                request
+                    .client
+                    .token_provider(request.source_id)
+                    .invalidate();
                 Err(Error::new(error::Kind::AuthExpiredToken))
             }
-            Err(error) => Err(Error::new(error::Kind::Other(error.to_string()))),
+            _ if response.is_success() => Ok(Response::new(response)),
+            _ => Err(Error::new(error::Kind::BadRequest(
+                String::from_utf8(response.body).unwrap_or("no text body".to_string()),
+            ))),
         }
     }
 
-    fn create_url(
-        client: &Client,
-        path: &str,
-        matrix_args: &[(&str, &str)],
-    ) -> Result<reqwest::Url> {
-        let mut url = match Url::parse(Self::BASE_URL) {
-            Ok(base) => base,
-            Err(err) => return Err(Error::new(error::Kind::Parse(err.to_string()))),
-        };
-        url.set_path(&format!(
-            "{}/{}{}",
-            client.credentials().account_id().as_str(),
-            path,
-            matrix_args
-                .iter()
-                .map(|(key, val)| format!(";{key}={val}"))
-                .collect::<String>()
-        ));
-        Ok(url)
+    /// Joins the client's base URL, account ID, `path` and matrix parameters.
+    fn create_url(client: &Client, path: &str, matrix_args: &[(&str, &str)]) -> Result<String> {
+        let base = client.api_url().trim_end_matches('/');
+        let valid = ["https://", "http://"]
+            .iter()
+            .any(|scheme| base.len() > scheme.len() && base.starts_with(scheme))
+            && !base.contains(['?', '#']);
+        if !valid {
+            return Err(Error::new(error::Kind::Parse(format!(
+                "invalid base URL '{base}'"
+            ))));
+        }
+        let matrix_args: String = matrix_args
+            .iter()
+            .map(|(key, val)| format!(";{key}={val}"))
+            .collect();
+        Ok(format!(
+            "{base}/{}/{path}{matrix_args}",
+            path_segment(client.account_id().as_str())
+        ))
     }
 }
+
+/// Percent-encodes a value for use as a single path segment, e.g. a recipient address.
+pub(crate) fn path_segment(val: &str) -> String {
+    val.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'@' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
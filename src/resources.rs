@@ -1,3 +1,5 @@
+use crate::auth::Secret;
+use crate::error;
 use crate::ty;
 use serde::{Deserialize, Serialize};
 
@@ -129,86 +131,182 @@ impl AttachmentBuilder {
 
 /// Undeliverable message.
 #[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct BouncedMessage {
     /// The time flowmailer was done processing this message.
-    pub backend_done: ty::Date,
+    pub backend_done: Option<ty::Date>,
     /// The time flowmailer started processing this message.
-    pub backend_start: ty::Date,
+    pub backend_start: Option<ty::Date>,
     /// Date on which the message was bounced.
-    pub bounce_received: ty::Date,
-    pub bounce_snippet: ty::String,
-    pub bounce_sub_type: ty::String,
-    pub bounce_type: ty::String,
+    pub bounce_received: Option<ty::Date>,
+    pub bounce_snippet: Option<ty::String>,
+    pub bounce_sub_type: Option<ty::String>,
+    pub bounce_type: Option<ty::String>,
     /// Message events.
     /// Ordered by received, new events first.
+    /// Only filled when the `addevents` parameter is `true`.
+    #[serde(default)]
     pub events: Box<[MessageEvent]>,
     /// Flow this message was processed in.
-    pub flow: ObjectDescription,
+    pub flow: Option<ObjectDescription>,
     /// The email address in From email header.
-    pub from: ty::String,
+    pub from: Option<ty::String>,
     /// The address in From email header.
-    pub from_address: Address,
+    pub from_address: Option<Address>,
     /// E-Mail headers of the submitted email message.
     /// Only applicable when `messageType` = `EMAIL` and `addheaders` parameter is `true`.
+    #[serde(default)]
     pub headers_in: Box<[Header]>,
     /// Headers of the final e-mail.
     /// Only applicable when `messageType` = `EMAIL` and `addheaders` parameter is `true`.
+    #[serde(default)]
     pub headers_out: Box<[Header]>,
     /// Message id.
     pub id: ty::String,
     /// Link for the message details page. With resend button.
-    pub message_details_link: ty::String,
+    pub message_details_link: Option<ty::String>,
     /// Content of the `Message-ID` email header.
-    pub message_id_header: ty::String,
-    /// Message type: EMAIL, SMS or LETTER.
-    pub message_type: ty::String,
+    pub message_id_header: Option<ty::String>,
+    pub message_type: ty::MessageType,
     /// Last online link.
-    pub online_link: ty::String,
+    pub online_link: Option<ty::String>,
     /// Recipient address.
-    pub recipient_adr: ty::String,
+    pub recipient_address: ty::String,
     /// Sender address.
-    pub sender_address: ty::String,
+    pub sender_address: Option<ty::String>,
     /// Source system that submitted this message.
-    pub source: ObjectDescription,
+    pub source: Option<ObjectDescription>,
     /// Current message status.
     pub status: ty::String,
     /// Message subject.
     /// Only applicable when `messageType` = `EMAIL`.
-    pub subject: ty::String,
+    pub subject: Option<ty::String>,
     /// The time this message was submitted to flowmailer.
-    pub submitted: ty::String,
+    pub submitted: Option<ty::Date>,
     /// Message tags, only available for api calls with `addtags` = `true`.
+    #[serde(default)]
     pub tags: Box<[ty::String]>,
     /// The recipients in the To email header.
+    #[serde(default)]
     pub to_address_list: Box<[Address]>,
     /// The SMTP transaction id, returned with the SMTP `250` response.
-    pub transaction_id: ty::String,
+    pub transaction_id: Option<ty::String>,
 }
 
-#[derive(Serialize, Deserialize)]
+impl BouncedMessage {
+    /// Recipient address, see [`BouncedMessage::recipient_address`].
+    #[deprecated(note = "use the `recipient_address` field")]
+    pub fn recipient_adr(&self) -> &str {
+        &self.recipient_address
+    }
+
+    /// Classifies the bounce as hard or soft.
+    ///
+    /// Uses the bounce type reported by flowmailer, falling back to the sub type of the most
+    /// recent `BOUNCE` event and finally to the SMTP status code in the bounce snippet.
+    /// Returns None when none of these are conclusive.
+    pub fn classify(&self) -> Option<ty::BounceType> {
+        let bounce_event = self
+            .events
+            .iter()
+            .find(|event| event.ty == ty::message_event::Type::Bounce);
+        bounce_type_from_name(self.bounce_type.as_deref())
+            .or_else(|| bounce_type_from_name(bounce_event?.sub_type.as_deref()))
+            .or_else(|| bounce_type_from_snippet(self.bounce_snippet.as_deref()))
+            .or_else(|| bounce_type_from_snippet(bounce_event?.snippet.as_deref()))
+    }
+}
+
+fn bounce_type_from_name(name: Option<&str>) -> Option<ty::BounceType> {
+    let name = name?;
+    if name.eq_ignore_ascii_case("hard") {
+        Some(ty::BounceType::Hard)
+    } else if name.eq_ignore_ascii_case("soft") {
+        Some(ty::BounceType::Soft)
+    } else {
+        None
+    }
+}
+
+/// Looks for the first SMTP reply code (`550`) or enhanced status code (`5.1.1`).
+fn bounce_type_from_snippet(snippet: Option<&str>) -> Option<ty::BounceType> {
+    snippet?
+        .split(|c: char| c.is_whitespace() || c == ':' || c == '-')
+        .filter(|word| is_smtp_code(word))
+        .find_map(|code| match code.as_bytes()[0] {
+            b'5' => Some(ty::BounceType::Hard),
+            b'4' => Some(ty::BounceType::Soft),
+            _ => None,
+        })
+}
+
+fn is_smtp_code(word: &str) -> bool {
+    let is_number = |part: &str| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit());
+    match word.split('.').collect::<Vec<_>>().as_slice() {
+        [code] => code.len() == 3 && is_number(code),
+        [class, subject, detail] => {
+            class.len() == 1 && is_number(class) && is_number(subject) && is_number(detail)
+        }
+        _ => false,
+    }
+}
+
+/// Credentials a source uses to submit messages over SMTP or SMPP.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Credentials {
     /// Allowed IP addresses that can connect to Flowmailer using these credentials.
+    #[serde(default)]
     pub allowed_addresses: Box<[ty::String]>,
+    #[serde(default)]
     pub allowed_senders: Box<[ty::String]>,
     /// Contact info for these credentials.
-    pub contact_info: ty::String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contact_info: Option<ty::String>,
     /// Description.
     pub description: ty::String,
     /// Source credentials ID.
-    pub id: ty::String,
-    pub inbound_domain: ty::String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<ty::String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inbound_domain: Option<ty::String>,
+    #[serde(default)]
     pub inbound_recipients: Box<[InboundRecipient]>,
-    /// Password.
-    pub password: ty::String,
-    /// Protocol: `SMTP`, `SMTP_RCPT`, `SMTP_HEADER`, `SMTP_IPONLY` or `SMPP`.
-    pub protocol: ty::String,
+    /// Password. Left unchanged on update when `None`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub password: Option<Secret>,
+    pub protocol: ty::credentials::Protocol,
     /// Source ID.
-    pub source_id: ty::String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_id: Option<ty::String>,
     /// Username.
     pub username: ty::String,
 }
 
-#[derive(Serialize, Deserialize)]
+impl Credentials {
+    pub fn new(
+        description: impl Into<String>,
+        protocol: ty::credentials::Protocol,
+        username: impl Into<String>,
+        password: impl Into<String>,
+    ) -> Self {
+        Self {
+            allowed_addresses: Box::new([]),
+            allowed_senders: Box::new([]),
+            contact_info: None,
+            description: description.into(),
+            id: None,
+            inbound_domain: None,
+            inbound_recipients: Box::new([]),
+            password: Some(Secret::new(password.into())),
+            protocol,
+            source_id: None,
+            username: username.into(),
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct DataExpression {
     /// Expression.
     pub expression: ty::String,
@@ -216,6 +314,24 @@ pub struct DataExpression {
     pub value: ty::String,
 }
 
+impl DataExpression {
+    /// Evaluates a variable path expression such as `${order.type}` against message data.
+    pub(crate) fn matches(&self, data: &Object) -> bool {
+        let path = self.expression.trim();
+        let path = path
+            .strip_prefix("${")
+            .and_then(|path| path.strip_suffix('}'))
+            .unwrap_or(path)
+            .trim();
+        let value = path.split('.').try_fold(data, |value, key| value.get(key));
+        match value {
+            Some(Object::String(value)) => *value == self.value,
+            Some(Object::Null) | None => false,
+            Some(value) => serde_json::from_str::<Object>(&self.value).is_ok_and(|v| v == *value),
+        }
+    }
+}
+
 /// Data set with statistics.
 #[derive(Serialize, Deserialize)]
 pub struct DataSet {
@@ -232,7 +348,22 @@ pub struct DataSets {
     pub list: Box<[DataSet]>,
 }
 
-#[derive(Serialize, Deserialize)]
+impl DataSets {
+    /// Returns the data set for a metric, if it was returned.
+    pub fn get(&self, metric: ty::Metric) -> Option<&DataSet> {
+        self.list.iter().find(|set| set.name == metric.as_str())
+    }
+}
+
+impl DataSet {
+    /// Sum of all sample values in this data set.
+    pub fn total(&self) -> ty::Int {
+        self.samples.iter().map(|sample| sample.value).sum()
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct DkimKey {
     pub cname_target: ty::String,
     pub domain: ty::String,
@@ -241,39 +372,40 @@ pub struct DkimKey {
 }
 
 /// DNS record that should be configured.
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct DnsRecord {
     /// Error messages for this DNS record.
     /// Only filled when DNS records are validated.
+    #[serde(default)]
     pub error_messages: Box<[ty::String]>,
     /// Record name.
     pub name: ty::String,
     /// Current record status.
     /// Only filled when DNS records are validated.
-    /// Possible values:
-    /// `UNKNOWN: We had an error while validating this DNS record`,
-    /// `ERROR: DNS record is not correct`,
-    /// `WARNING: DNS record is functional but could be improved` or
-    /// `OK: DNS record is ok`.
-    pub status: ty::String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<ty::dns_record::Status>,
     /// Record type.
+    #[serde(rename = "type")]
     pub ty: ty::String,
     /// Record value description in HTML.
     pub value: ty::String,
     /// Warning messages for this DNS record.
     /// Only filled when DNS records are validated.
+    #[serde(default)]
     pub warning_messages: Box<[ty::String]>,
-    pub warnings: ty::Empty,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Error {
+    #[serde(default)]
     pub arguments: Box<[Object]>,
-    pub code: ty::String,
-    pub default_message: ty::String,
-    pub field: ty::String,
-    pub object_name: ty::String,
-    pub rejected_value: Object,
+    pub code: Option<ty::String>,
+    pub default_message: Option<ty::String>,
+    pub field: Option<ty::String>,
+    pub object_name: Option<ty::String>,
+    pub rejected_value: Option<Object>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -283,38 +415,71 @@ pub struct Errors {
 }
 
 #[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct EventFlow {
     /// Flow description.
     pub description: ty::String,
     /// Flow ID.
-    pub id: ty::String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<ty::String>,
     /// Id of the flow parent.
-    pub parent_id: ty::String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<ty::String>,
     /// Flow steps that each message in this flow will be processed by.
     pub steps: Box<[FlowStep]>,
 }
 
 /// Conditions which must be true for an event to use a flow.
+/// Conditions that are not set match every event.
 #[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct EventFlowRuleSimple {
-    pub data_expression: DataExpression,
-    pub data_expression_value: ty::String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_expression: Option<DataExpression>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_expression_value: Option<ty::String>,
     /// Data expressions which must be present in the message.
+    #[serde(default)]
     pub data_expressions: Box<[DataExpression]>,
     /// Flow ID of the flow the events will use if the specified conditions are true.
     pub event_flow_id: ty::String,
     /// Match type of the event.
-    pub event_type: ty::String,
-    pub header: Header,
-    pub header_value: ty::String,
+    pub event_type: ty::message_event::Type,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub header: Option<Header>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub header_value: Option<ty::String>,
     /// Email headers which must be present in the message.
+    #[serde(default)]
     pub headers: Box<[Header]>,
     /// Name of the link that was clicked for CLICK events.
-    pub link_name: ty::String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link_name: Option<ty::String>,
     /// Url of the link that was clicked for CLICK events.
-    pub link_target: ty::String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link_target: Option<ty::String>,
     /// Message Flow ID which handled the original message.
-    pub message_flow_id: ty::String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message_flow_id: Option<ty::String>,
+}
+
+impl EventFlowRuleSimple {
+    /// Creates a rule that sends every event of `event_type` to the event flow.
+    pub fn new(event_type: ty::message_event::Type, event_flow_id: impl Into<String>) -> Self {
+        Self {
+            data_expression: None,
+            data_expression_value: None,
+            data_expressions: Box::new([]),
+            event_flow_id: event_flow_id.into(),
+            event_type,
+            header: None,
+            header_value: None,
+            headers: Box::new([]),
+            link_name: None,
+            link_target: None,
+            message_flow_id: None,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -325,27 +490,28 @@ pub struct Exception {
 
 /// Filtered recipient address.
 #[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Filter {
     /// Account ID.
-    pub account_id: ty::String,
+    pub account_id: Option<ty::String>,
     /// Filtered recipient address.
     pub address: ty::String,
     /// Date on which this filter was added.
-    pub date: ty::Date,
+    pub date: Option<ty::Date>,
     /// Date on which this filter expires.
-    pub expires_on: ty::Date,
+    pub expires_on: Option<ty::Date>,
     /// Filter ID.
     pub id: ty::String,
     /// Message event that was the reason for creating this filter.
-    pub message_return: MessageReturn,
+    pub message_return: Option<MessageReturn>,
     /// This filter is for message type: `EMAIL` or `SMS`.
-    pub message_type: ty::String,
+    pub message_type: ty::MessageType,
     /// Filter reason.
-    pub reason: ty::String,
+    pub reason: Option<ty::String>,
 }
 
 /// Message flow.
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Flow {
     /// Flow description.
     pub description: ty::String,
@@ -364,169 +530,341 @@ pub struct Flow {
     pub template_id: ty::String,
 }
 
+impl Flow {
+    /// Replaces the first step of the same type as `step`, or appends it when there is none.
+    /// Use [`Flow::with_step_at`] when the flow has several steps of the same type.
+    pub fn with_step(mut self, step: FlowStep) -> Self {
+        let mut steps = self.steps.into_vec();
+        match steps.iter_mut().find(|current| current.ty() == step.ty()) {
+            Some(current) => *current = step,
+            None => steps.push(step),
+        }
+        self.steps = steps.into_boxed_slice();
+        self
+    }
+
+    /// Replaces the step at `index`, or appends `step` when `index` is past the last step.
+    pub fn with_step_at(mut self, index: usize, step: FlowStep) -> Self {
+        let mut steps = self.steps.into_vec();
+        match steps.get_mut(index) {
+            Some(current) => *current = step,
+            None => steps.push(step),
+        }
+        self.steps = steps.into_boxed_slice();
+        self
+    }
+}
+
 /// Conditions which must be true for a message to use a flow.
-#[derive(Serialize, Deserialize)]
+/// Conditions that are not set match every message.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct FlowRuleSimple {
     /// Data expressions which must be present in the message.
+    #[serde(default)]
     pub data_expressions: Box<[DataExpression]>,
     /// Flow ID of the flow the messages will use if the specified conditions are true.
     pub flow_id: ty::String,
     /// Flow selector.
-    pub flow_selector: ty::String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub flow_selector: Option<ty::String>,
     /// Email headers which must be present in the message.
+    #[serde(default)]
     pub headers: Box<[Header]>,
     /// Sender email address or phone number.
-    pub sender: ty::String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sender: Option<ty::String>,
     /// Source ID which must have submitted the message.
-    pub source_id: ty::String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_id: Option<ty::String>,
+}
+
+impl FlowRuleSimple {
+    /// Returns true when `message`, submitted through `source_id`, meets every condition of this rule.
+    ///
+    /// Data expressions are evaluated locally and only support variable paths such as
+    /// `${order.type}`, which are looked up in the message data.
+    pub fn matches(&self, message: &SubmitMessage, source_id: Option<&str>) -> bool {
+        let flow_selector_matches = match &self.flow_selector {
+            Some(selector) => message.flow_selector.as_ref() == Some(selector),
+            None => true,
+        };
+        let sender_matches = match &self.sender {
+            Some(sender) => sender.eq_ignore_ascii_case(&message.sender_address),
+            None => true,
+        };
+        let source_matches = match &self.source_id {
+            Some(id) => source_id == Some(id.as_str()),
+            None => true,
+        };
+        flow_selector_matches
+            && sender_matches
+            && source_matches
+            && self.headers.iter().all(|header| {
+                message.headers.iter().any(|candidate| {
+                    candidate.name.eq_ignore_ascii_case(&header.name)
+                        && candidate.value == header.value
+                })
+            })
+            && self
+                .data_expressions
+                .iter()
+                .all(|expression| expression.matches(&message.data))
+    }
+
+    /// Returns the first rule in `rules`, in slice order, whose conditions match `message`
+    /// according to [`FlowRuleSimple::matches`].
+    pub fn route<'a>(
+        rules: &'a [FlowRuleSimple],
+        message: &SubmitMessage,
+        source_id: Option<&str>,
+    ) -> Option<&'a FlowRuleSimple> {
+        rules.iter().find(|rule| rule.matches(message, source_id))
+    }
 }
 
 /// A processing step in a flow.
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct FlowStep {
-    /// Only applicable and required when `type` = `addAttachment`.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    #[serde(rename = "addAttachment")]
-    pub add_attachment: Option<FlowStepAddAttachment>,
-    /// Only applicable when `type` = `aggregate`.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub aggregate: Option<FlowStepAggregate>,
-    /// Comma separated list of link domains to modify for analytics parameters.
-    /// Only applicable when `type` = `analytics`.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    #[serde(rename = "applyToLinkDomains")]
-    pub apply_to_link_domains: Option<ty::String>,
-    /// Only applicable and required when `type` = `archive`.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub archive: Option<FlowStepArchive>,
-    /// Only applicable when `type` = `qamail`.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub divisor: Option<ty::Int>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    #[serde(rename = "enabledExpression")]
-    pub enabled_expression: Option<ty::String>,
-    /// Indicates whether the contact is required or not.
-    /// Only applicable when `type` = `mailPlusContact`.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    #[serde(rename = "errorOnNotFound")]
-    pub error_on_not_found: Option<ty::Bool>,
-    /// Only applicable and required when `type` = `externalContent`.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    #[serde(rename = "externalContent")]
-    pub external_content: Option<FlowStepExternalContent>,
-    /// Only applicable and required when `type` = `externalData`.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    #[serde(rename = "externalData")]
-    pub external_data: Option<FlowStepExternalData>,
-    /// Only applicable and required when `type` = `extractdata`.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    #[serde(rename = "extractData")]
-    pub extract_data: Option<FlowStepExtractData>,
     /// Flow step ID.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub id: Option<ty::String>,
-    /// Credentials to use for retrieving contacts from MailPlus.
-    /// Only applicable when `type` = `mailPlusContact`.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    #[serde(rename = "mailPlusApiCredentials")]
-    pub mail_plus_api_credentials: Option<MailPlusAPICredentials>,
-    /// Overwrite existing URL Parameters in links.
-    /// Only applicable when `type` = `analytics`.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    #[serde(rename = "overwriteUrlParameters")]
-    pub overwrite_url_parameters: Option<ty::Bool>,
-    /// Only applicable when `type` = `resubmitMessage`.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    #[serde(rename = "resubmitMessage")]
-    pub resubmit_message: Option<FlowStepResubmitMessage>,
-    /// Only applicable and required when `type` = `rewriteRecipient`.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    #[serde(rename = "rewriteRecipient")]
-    pub rewrite_recipient: Option<FlowStepRewriteRecipient>,
-    /// Only applicable when `type` = `schedule`.
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub schedule: Option<FlowStepSchedule>,
-    /// Only applicable and required when `type` = `addHeader`.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    #[serde(rename = "setHeader")]
-    pub set_header: Option<FlowStepSetHeader>,
-    /// Only applicable and required when `type` = `setSender`.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    #[serde(rename = "setSender")]
-    pub set_sender: Option<FlowStepSetSender>,
-    /// Template for the new subject. Template variables can be used in this field.
-    /// Only applicable when `type` = `subject`.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    #[serde(rename = "subjectTemplate")]
-    pub subject_template: Option<ty::String>,
-    /// Only applicable when `type` = `template`.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub template: Option<ObjectDescription>,
-    // Flow step type.
-    #[serde(rename = "type")]
-    pub ty: ty::flow_step::Type,
-    /// Email address the BCC mail will be sent to.
-    /// Only applicable and required when `type` = `qamail`.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub to: Option<ty::String>,
-    /// URL Parameters to add to all links. Template variables can be used in this field.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    #[serde(rename = "urlParametersTemplate")]
-    pub url_parameters_template: Option<ty::String>,
+    pub enabled_expression: Option<ty::String>,
+    /// Flow step type and its settings.
+    #[serde(flatten)]
+    pub kind: FlowStepKind,
+}
+
+/// Flow step type with the settings applicable to that type.
+/// Serialized with the `type` field as tag.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum FlowStepKind {
+    #[serde(rename = "analytics", rename_all = "camelCase")]
+    Analytics {
+        /// Comma separated list of link domains to modify for analytics parameters.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        apply_to_link_domains: Option<ty::String>,
+        /// Overwrite existing URL Parameters in links.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        overwrite_url_parameters: Option<ty::Bool>,
+        /// URL Parameters to add to all links. Template variables can be used in this field.
+        url_parameters_template: ty::String,
+    },
+    #[serde(rename = "qamail")]
+    QAmail {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        divisor: Option<ty::Int>,
+        /// Email address the BCC mail will be sent to.
+        to: ty::String,
+    },
+    #[serde(rename = "template")]
+    Template { template: ObjectDescription },
+    #[serde(rename = "subject", rename_all = "camelCase")]
+    Subject {
+        /// Template for the new subject. Template variables can be used in this field.
+        subject_template: ty::String,
+    },
+    #[serde(rename = "setSender", rename_all = "camelCase")]
+    SetSender { set_sender: FlowStepSetSender },
+    #[serde(rename = "addHeader", rename_all = "camelCase")]
+    AddHeader { set_header: FlowStepSetHeader },
+    #[serde(rename = "schedule")]
+    Schedule {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        schedule: Option<FlowStepSchedule>,
+    },
+    #[serde(rename = "rewriteRecipient", rename_all = "camelCase")]
+    RewriteRecipient {
+        rewrite_recipient: FlowStepRewriteRecipient,
+    },
+    #[serde(rename = "resubmitMessage", rename_all = "camelCase")]
+    ResubmitMessage {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        resubmit_message: Option<FlowStepResubmitMessage>,
+    },
+    #[serde(rename = "mailPlusContact", rename_all = "camelCase")]
+    MailPlusContact {
+        /// Indicates whether the contact is required or not.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error_on_not_found: Option<ty::Bool>,
+        /// Credentials to use for retrieving contacts from MailPlus.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        mail_plus_api_credentials: Option<MailPlusAPICredentials>,
+    },
+    #[serde(rename = "extractdata", rename_all = "camelCase")]
+    ExtractData { extract_data: FlowStepExtractData },
+    #[serde(rename = "externalData", rename_all = "camelCase")]
+    ExternalData { external_data: FlowStepExternalData },
+    #[serde(rename = "externalContent", rename_all = "camelCase")]
+    ExternalContent {
+        external_content: FlowStepExternalContent,
+    },
+    #[serde(rename = "archive")]
+    Archive { archive: FlowStepArchive },
+    #[serde(rename = "aggregate")]
+    Aggregate {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        aggregate: Option<FlowStepAggregate>,
+    },
+    #[serde(rename = "addAttachment", rename_all = "camelCase")]
+    AddAttachment {
+        add_attachment: FlowStepAddAttachment,
+    },
+}
+
+impl FlowStepKind {
+    /// Returns the flow step type.
+    pub fn ty(&self) -> ty::flow_step::Type {
+        use ty::flow_step::Type;
+        match self {
+            Self::Analytics { .. } => Type::Analytics,
+            Self::QAmail { .. } => Type::QAmail,
+            Self::Template { .. } => Type::Template,
+            Self::Subject { .. } => Type::Subject,
+            Self::SetSender { .. } => Type::SetSender,
+            Self::AddHeader { .. } => Type::AddHeader,
+            Self::Schedule { .. } => Type::Schedule,
+            Self::RewriteRecipient { .. } => Type::RewriteRecipient,
+            Self::ResubmitMessage { .. } => Type::ResubmitMessage,
+            Self::MailPlusContact { .. } => Type::MailPlusContact,
+            Self::ExtractData { .. } => Type::ExtractData,
+            Self::ExternalData { .. } => Type::ExternalData,
+            Self::ExternalContent { .. } => Type::ExternalContent,
+            Self::Archive { .. } => Type::Archive,
+            Self::Aggregate { .. } => Type::Aggregate,
+            Self::AddAttachment { .. } => Type::AddAttachment,
+        }
+    }
 }
 
 impl FlowStep {
-    pub fn template(template: ObjectDescription) -> Self {
+    pub fn new(kind: FlowStepKind) -> Self {
         Self {
-            template: Some(template),
-            ..Self::fill_none(ty::flow_step::Type::Template)
+            id: None,
+            enabled_expression: None,
+            kind,
         }
     }
 
-    fn fill_none(ty: ty::flow_step::Type) -> Self {
-        Self {
-            ty,
-            add_attachment: None,
-            aggregate: None,
+    /// Only run this step when the expression evaluates to true.
+    pub fn enabled_expression(mut self, expression: impl Into<String>) -> Self {
+        self.enabled_expression = Some(expression.into());
+        self
+    }
+
+    pub fn ty(&self) -> ty::flow_step::Type {
+        self.kind.ty()
+    }
+
+    /// Adds URL parameters to all links.
+    pub fn analytics(url_parameters_template: impl Into<String>) -> Self {
+        Self::new(FlowStepKind::Analytics {
             apply_to_link_domains: None,
-            archive: None,
-            divisor: None,
-            enabled_expression: None,
-            error_on_not_found: None,
-            external_content: None,
-            external_data: None,
-            extract_data: None,
-            id: None,
-            mail_plus_api_credentials: None,
             overwrite_url_parameters: None,
-            resubmit_message: None,
-            rewrite_recipient: None,
-            schedule: None,
-            set_header: None,
-            set_sender: None,
-            subject_template: None,
-            template: None,
-            to: None,
-            url_parameters_template: None,
-        }
+            url_parameters_template: url_parameters_template.into(),
+        })
+    }
+
+    /// Sends a BCC of the message to `to`.
+    pub fn qamail(to: impl Into<String>) -> Self {
+        Self::new(FlowStepKind::QAmail {
+            divisor: None,
+            to: to.into(),
+        })
+    }
+
+    pub fn template(template: ObjectDescription) -> Self {
+        Self::new(FlowStepKind::Template { template })
+    }
+
+    pub fn subject(subject_template: impl Into<String>) -> Self {
+        Self::new(FlowStepKind::Subject {
+            subject_template: subject_template.into(),
+        })
+    }
+
+    pub fn set_sender(set_sender: FlowStepSetSender) -> Self {
+        Self::new(FlowStepKind::SetSender { set_sender })
+    }
+
+    pub fn add_header(set_header: FlowStepSetHeader) -> Self {
+        Self::new(FlowStepKind::AddHeader { set_header })
+    }
+
+    pub fn schedule(schedule: FlowStepSchedule) -> Self {
+        Self::new(FlowStepKind::Schedule {
+            schedule: Some(schedule),
+        })
+    }
+
+    pub fn rewrite_recipient(rewrite_recipient: FlowStepRewriteRecipient) -> Self {
+        Self::new(FlowStepKind::RewriteRecipient { rewrite_recipient })
+    }
+
+    pub fn resubmit_message(resubmit_message: FlowStepResubmitMessage) -> Self {
+        Self::new(FlowStepKind::ResubmitMessage {
+            resubmit_message: Some(resubmit_message),
+        })
+    }
+
+    pub fn mail_plus_contact(
+        credentials: MailPlusAPICredentials,
+        error_on_not_found: bool,
+    ) -> Self {
+        Self::new(FlowStepKind::MailPlusContact {
+            error_on_not_found: Some(error_on_not_found),
+            mail_plus_api_credentials: Some(credentials),
+        })
+    }
+
+    pub fn extract_data(extract_data: FlowStepExtractData) -> Self {
+        Self::new(FlowStepKind::ExtractData { extract_data })
+    }
+
+    pub fn external_data(external_data: FlowStepExternalData) -> Self {
+        Self::new(FlowStepKind::ExternalData { external_data })
+    }
+
+    pub fn external_content(external_content: FlowStepExternalContent) -> Self {
+        Self::new(FlowStepKind::ExternalContent { external_content })
+    }
+
+    pub fn archive(archive: FlowStepArchive) -> Self {
+        Self::new(FlowStepKind::Archive { archive })
+    }
+
+    pub fn aggregate(aggregate: FlowStepAggregate) -> Self {
+        Self::new(FlowStepKind::Aggregate {
+            aggregate: Some(aggregate),
+        })
+    }
+
+    pub fn add_attachment(add_attachment: FlowStepAddAttachment) -> Self {
+        Self::new(FlowStepKind::AddAttachment { add_attachment })
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct FlowStepAddAttachment {
     /// URL to load the external content from.
     /// Template variables can be used in this field.
     pub url_template: ty::String,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct FlowStepAggregate {
     pub always_send_first: ty::Bool,
     pub max_time_seconds: ty::Int,
     pub quiet_time_seconds: ty::Int,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct FlowStepArchive {
     /// Indicates whether this archive is available for online version link.
     pub online_link: ty::Bool,
@@ -536,7 +874,8 @@ pub struct FlowStepArchive {
     pub retention: ty::String,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct FlowStepExternalContent {
     /// Variable to store the external content in.
     pub result_variable: ty::String,
@@ -545,7 +884,8 @@ pub struct FlowStepExternalContent {
     pub url_template: ty::String,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct FlowStepExternalData {
     /// When true the result variable will be filled with a structure that also contains the response headers.
     /// When false the result variable will be filled with just the response body.
@@ -568,7 +908,8 @@ pub struct FlowStepExternalData {
     pub url_template: ty::String,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct FlowStepExtractData {
     pub data_type: ty::String,
     pub filename: ty::String,
@@ -578,7 +919,8 @@ pub struct FlowStepExtractData {
     pub selector: ty::String,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct FlowStepResubmitMessage {
     /// Account to submit the message to.
     pub account: ObjectDescription,
@@ -593,14 +935,16 @@ pub struct FlowStepResubmitMessage {
     pub source: ObjectDescription,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct FlowStepRewriteRecipient {
     pub message_type: ty::String,
     pub recipient_name_template: ty::String,
     pub recipient_template: ty::String,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct FlowStepSchedule {
     pub max_messages_per_hour: ty::Int,
     pub offset_type: ty::String,
@@ -616,7 +960,8 @@ pub struct FlowStepSchedule {
     pub time_zone: ty::String,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct FlowStepSetHeader {
     /// Name of the header to add to the email.
     pub header_name: ty::String,
@@ -624,7 +969,8 @@ pub struct FlowStepSetHeader {
     pub header_value: ty::String,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct FlowStepSetSender {
     pub sender_name_template: ty::String,
     pub sender_set_name: ty::Bool,
@@ -633,19 +979,53 @@ pub struct FlowStepSetSender {
 
 /// Message flow template.
 #[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct FlowTemplate {
     /// Flow description.
     pub description: ty::String,
-    pub editable: ty::Bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub editable: Option<ty::Bool>,
     /// Flow template ID.
-    pub id: ty::String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<ty::String>,
     /// Flow steps that each message in this flow will be processed by.
     pub steps: Box<[FlowStep]>,
     /// Id of the parent flow.
-    pub template_id: ty::String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub template_id: Option<ty::String>,
 }
 
-#[derive(Serialize, Deserialize)]
+impl FlowTemplate {
+    /// Creates a new flow based on this template, starting with a copy of its steps.
+    /// The copied steps have no ID, so flowmailer creates new steps for the flow.
+    /// Use [`Flow::with_step`] or [`Flow::with_step_at`] to override individual steps.
+    /// Fails when this template has no ID, i.e. was not fetched from flowmailer.
+    pub fn to_flow(&self, description: impl Into<String>) -> error::Result<Flow> {
+        let template_id = self.id.clone().ok_or_else(|| {
+            error::Error::new(error::Kind::Other(
+                "cannot create a flow from a flow template without ID".to_string(),
+            ))
+        })?;
+        let steps = self
+            .steps
+            .iter()
+            .map(|step| FlowStep {
+                id: None,
+                ..step.clone()
+            })
+            .collect();
+        Ok(Flow {
+            description: description.into(),
+            id: None,
+            message_summary: None,
+            statistics: None,
+            steps,
+            template_id,
+        })
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Header {
     /// Header name.
     pub name: ty::String,
@@ -653,14 +1033,17 @@ pub struct Header {
     pub value: ty::String,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct InboundRecipient {
+    #[serde(rename = "destinationRecipient")]
     pub destination_receipient: ty::String,
     pub inbound_address: ty::String,
 }
 
 /// Spotler API credentials.
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct MailPlusAPICredentials {
     /// Consumer key.
     pub consumer_key: ty::String,
@@ -669,54 +1052,62 @@ pub struct MailPlusAPICredentials {
 }
 
 #[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Message {
     /// The time flowmailer was done processing this message.
-    pub backend_done: ty::Date,
+    pub backend_done: Option<ty::Date>,
     /// The time flowmailer started processing this message.
-    pub backend_start: ty::Date,
+    pub backend_start: Option<ty::Date>,
     /// Message events.
     /// Ordered by received, new events first.
+    /// Only filled when the `addevents` parameter is `true`.
+    #[serde(default)]
     pub events: Box<[MessageEvent]>,
     /// Flow this message was processed in.
-    pub flow: ObjectDescription,
+    pub flow: Option<ObjectDescription>,
     /// The email address in `From` email header.
-    pub from: ty::String,
+    pub from: Option<ty::String>,
     /// The address in `From` email header
-    pub from_address: Address,
+    pub from_address: Option<Address>,
     /// E-Mail headers of the submitted email message.
     /// Only applicable when `messageType` = `EMAIL` and `addheaders` parameter is `true`.
+    #[serde(default)]
     pub headers_in: Box<[Header]>,
     /// Headers of the final e-mail.
     /// Only applicable when `messageType` = `EMAIL` and `addheaders` parameter is `true`.
+    #[serde(default)]
     pub headers_out: Box<[Header]>,
     /// Message id.
     pub id: ty::String,
     /// Link for the message details page. With resend button.
-    pub message_details_link: ty::String,
+    pub message_details_link: Option<ty::String>,
     /// Content of the `Message-ID` email header.
-    pub message_id_header: ty::String,
+    pub message_id_header: Option<ty::String>,
     pub message_type: ty::MessageType,
     /// Last online link.
-    pub online_link: ty::String,
+    /// Only filled when the `addonlinelink` parameter is `true`.
+    pub online_link: Option<ty::String>,
     /// Recipient address.
     pub recipient_address: ty::String,
     /// Sender address.
-    pub sender_address: ty::String,
+    pub sender_address: Option<ty::String>,
     /// Source system that submitted this message.
-    pub source: ObjectDescription,
+    pub source: Option<ObjectDescription>,
     /// Current message status.
     pub status: ty::String,
     /// Message subject.
     /// Only applicable when `messageType` = `EMAIL`.
-    pub subject: ty::String,
+    pub subject: Option<ty::String>,
     /// The time this message was submitted to flowmailer.
-    pub submitted: ty::Date,
+    pub submitted: Option<ty::Date>,
     /// Message tags, only available for api calls with `addtags` = `true`.
+    #[serde(default)]
     pub tags: Box<[ty::String]>,
     /// The recipients in the `To` email header.
+    #[serde(default)]
     pub to_address_list: Box<[Address]>,
     /// The SMTP transaction id, returned with the SMTP `250` response.
-    pub transaction_id: ty::String,
+    pub transaction_id: Option<ty::String>,
 }
 
 /// Archived message text and/or HTML.
@@ -747,42 +1138,45 @@ pub struct MessageArchive {
 
 /// Message event.
 #[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct MessageEvent {
     /// Event data.
-    pub data: ty::Base64,
-    pub device_category: ty::String,
+    pub data: Option<ty::Base64>,
+    pub device_category: Option<ty::String>,
     /// Event data.
-    pub extra_data: ty::Empty,
+    pub extra_data: Option<Object>,
     /// Message event ID.
     pub id: ty::String,
     /// Database insert date.
-    pub inserted: ty::Empty,
-    pub link_name: ty::String,
-    pub link_target: ty::String,
+    pub inserted: Option<ty::Date>,
+    pub link_name: Option<ty::String>,
+    pub link_target: Option<ty::String>,
     /// Message ID.
     pub message_id: ty::String,
     /// Message tags.
     /// Only filled for the `GET /{account_id}/message_events` api call when the parameter `addmessagetags` is `true`.
+    #[serde(default)]
     pub message_tags: Box<[ty::String]>,
     /// MTA that reported this event.
-    pub mta: ty::String,
-    pub operating_system: ty::String,
-    pub operating_system_version: ty::String,
+    pub mta: Option<ty::String>,
+    pub operating_system: Option<ty::String>,
+    pub operating_system_version: Option<ty::String>,
     pub received: ty::Date,
-    pub referer: ty::String,
-    pub remote_address: ty::String,
+    pub referer: Option<ty::String>,
+    pub remote_address: Option<ty::String>,
     /// Bounce snippet or SMTP conversation snippet.
-    pub snippet: ty::String,
+    pub snippet: Option<ty::String>,
     /// Bounce sub type.
-    pub sub_type: ty::Empty,
+    pub sub_type: Option<ty::String>,
     /// Custom event type.
-    pub tag: ty::Empty,
-    pub ty: ty::TyCustom,
-    pub user_agent: ty::String,
-    pub user_agent_display_name: ty::String,
-    pub user_agent_string: ty::String,
-    pub user_agent_type: ty::String,
-    pub user_agent_version: ty::String,
+    pub tag: Option<ty::String>,
+    #[serde(rename = "type")]
+    pub ty: ty::message_event::Type,
+    pub user_agent: Option<ty::String>,
+    pub user_agent_display_name: Option<ty::String>,
+    pub user_agent_string: Option<ty::String>,
+    pub user_agent_type: Option<ty::String>,
+    pub user_agent_version: Option<ty::String>,
 }
 
 /// Messages that could not be processed.
@@ -820,50 +1214,55 @@ pub struct MessageHold {
 
 /// Message event.
 #[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct MessageReturn {
     /// Event data.
-    pub data: ty::Base64,
-    pub device_category: ty::String,
+    pub data: Option<ty::Base64>,
+    pub device_category: Option<ty::String>,
     /// Event data.
-    pub extra_data: ty::Empty,
+    pub extra_data: Option<Object>,
     /// Message event ID.
     pub id: ty::String,
     /// Database insert date.
-    pub inserted: ty::Empty,
-    pub link_name: ty::String,
-    pub link_target: ty::String,
+    pub inserted: Option<ty::Date>,
+    pub link_name: Option<ty::String>,
+    pub link_target: Option<ty::String>,
     /// Message ID.
     pub message_id: ty::String,
     /// Message tags.
     /// Only filled for the `GET /{account_id}/message_events` api call when the parameter `addmessagetags` is `true`.
+    #[serde(default)]
     pub message_tags: Box<[ty::String]>,
     /// MTA that reported this event.
-    pub mta: ty::String,
-    pub operating_system: ty::String,
-    pub operating_system_version: ty::String,
+    pub mta: Option<ty::String>,
+    pub operating_system: Option<ty::String>,
+    pub operating_system_version: Option<ty::String>,
     /// Event date.
     pub received: ty::Date,
-    pub referer: ty::String,
-    pub remote_address: ty::String,
+    pub referer: Option<ty::String>,
+    pub remote_address: Option<ty::String>,
     /// Bounce snippet or SMTP conversation snippet.
-    pub snippet: ty::String,
-    pub source_mta: ty::Empty,
+    pub snippet: Option<ty::String>,
+    pub source_mta: Option<ty::String>,
     /// Bounce sub type.
-    pub sub_type: ty::Empty,
+    pub sub_type: Option<ty::String>,
     /// Custom event type.
-    pub tag: ty::Empty,
-    pub ty: ty::TyCustom,
-    pub user_agent: ty::String,
-    pub user_agent_display_name: ty::String,
-    pub user_agent_string: ty::String,
-    pub user_agent_type: ty::String,
-    pub user_agent_version: ty::String,
+    pub tag: Option<ty::String>,
+    #[serde(rename = "type")]
+    pub ty: ty::message_event::Type,
+    pub user_agent: Option<ty::String>,
+    pub user_agent_display_name: Option<ty::String>,
+    pub user_agent_string: Option<ty::String>,
+    pub user_agent_type: Option<ty::String>,
+    pub user_agent_version: Option<ty::String>,
 }
 
 /// Message statistics summary.
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct MessageSummary {
     /// Average delivery time in milliseconds.
+    #[serde(rename = "averageDeliverTimeMillis")]
     pub avarage_deliver_time_millis: ty::Int,
     /// Number of times a link has been clicked.
     pub clicked: ty::Int,
@@ -889,7 +1288,7 @@ pub struct NamedSample {
 }
 
 /// Generic resource model with an ID and description.
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct ObjectDescription {
     /// Resource description.
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -900,10 +1299,12 @@ pub struct ObjectDescription {
 
 /// Statistics for a single recipient.
 #[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Recipient {
     /// Recipient email address or phone number.
     pub address: ty::String,
     /// One or more filters for this recipient.
+    #[serde(default)]
     pub filters: Box<[Filter]>,
     /// Message statistics for this recipient.
     pub message_summary: MessageSummary,
@@ -915,7 +1316,7 @@ pub struct ResendMessage {
     pub recipient_address: ty::String,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Sample {
     pub timestamp: ty::Date,
     pub value: ty::Int,
@@ -947,134 +1348,320 @@ pub struct SampleMessage {
 /// 1. `POST /{account_id}/sender_domains/validate`
 /// 2. `GET /{account_id}/sender_domains/{domain_id}` when `validate` parameter is `true`
 /// 3. `GET /{account_id}/sender_domains/by_domain/{domain}` when `validate` parameter is `true`
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct SenderDomain {
     /// List of DNS records that should exist.
+    #[serde(default, skip_serializing)]
     pub dns_records: Box<[DnsRecord]>,
     /// ID of this SenderDomain.
-    pub id: ty::String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<ty::String>,
     /// Domain used for bounce receiving, usually a subdomain of the `senderDomain`.
     pub return_path_domain: ty::String,
     /// Domain used to select this SenderDomain for emails with a matching `From` header.
     pub sender_domain: ty::String,
     /// Only filled when DNS records are validated.
+    #[serde(default, skip_serializing)]
     pub warnings: Box<[Error]>,
     /// Domain used for online tracking, usually a subdomain of the `senderDomain`.
     pub web_domain: ty::String,
 }
 
+impl SenderDomain {
+    pub fn new(
+        sender_domain: impl Into<String>,
+        return_path_domain: impl Into<String>,
+        web_domain: impl Into<String>,
+    ) -> Self {
+        Self {
+            dns_records: Box::new([]),
+            id: None,
+            return_path_domain: return_path_domain.into(),
+            sender_domain: sender_domain.into(),
+            warnings: Box::new([]),
+            web_domain: web_domain.into(),
+        }
+    }
+
+    /// Returns the validated DNS records that are not `OK`.
+    pub fn failing_records(&self) -> impl Iterator<Item = &DnsRecord> {
+        self.dns_records.iter().filter(|record| {
+            record
+                .status
+                .is_some_and(|status| status != ty::dns_record::Status::Ok)
+        })
+    }
+}
+
 /// Sender identities are used to rewrite sender information.
 /// Called 'sender rewrites' in Flowmailer dashboard.
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct SenderIdentity {
+    /// Use this identity when no other identity matches.
+    #[serde(default)]
     pub account_fallback: ty::Bool,
     pub description: ty::String,
+    #[serde(default)]
     pub dkim_keys: Box<[DkimKey]>,
-    pub id: ty::String,
+    /// Sender identity ID.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<ty::String>,
     pub return_path_domain: ty::String,
-    pub selection_type: ty::String,
+    /// What `selection_value` is compared against.
+    pub selection_type: ty::sender_identity::SelectionType,
+    /// Account ID, flow ID, domain or email address, depending on `selection_type`.
     pub selection_value: ty::String,
     pub sender_email: ty::String,
     pub sender_name: ty::String,
     pub web_domain: ty::String,
 }
 
-#[derive(Serialize, Deserialize)]
+impl SenderIdentity {
+    pub fn new(
+        description: impl Into<String>,
+        selection_type: ty::sender_identity::SelectionType,
+        selection_value: impl Into<String>,
+        sender_email: impl Into<String>,
+        sender_name: impl Into<String>,
+    ) -> Self {
+        Self {
+            account_fallback: false,
+            description: description.into(),
+            dkim_keys: Box::new([]),
+            id: None,
+            return_path_domain: String::new(),
+            selection_type,
+            selection_value: selection_value.into(),
+            sender_email: sender_email.into(),
+            sender_name: sender_name.into(),
+            web_domain: String::new(),
+        }
+    }
+
+    /// Returns whether this identity is selected by `sender` or `flow_id`,
+    /// ignoring the account fallback.
+    pub fn matches(&self, sender: &str, flow_id: Option<&str>) -> bool {
+        use ty::sender_identity::SelectionType;
+
+        let domain = sender.rsplit_once('@').map(|(_, domain)| domain);
+        match self.selection_type {
+            SelectionType::Email => self.selection_value.eq_ignore_ascii_case(sender),
+            SelectionType::Domain => {
+                domain.is_some_and(|domain| self.selection_value.eq_ignore_ascii_case(domain))
+            }
+            SelectionType::Flow => flow_id == Some(self.selection_value.as_str()),
+            SelectionType::Account => false,
+        }
+    }
+
+    /// Returns the identity in `identities` that a message from `sender` processed by `flow_id`
+    /// would use. The most specific selection wins: email address, then domain, then flow,
+    /// then the account-wide identity, then an identity marked as account fallback.
+    pub fn resolve<'a>(
+        identities: &'a [SenderIdentity],
+        sender: &str,
+        flow_id: Option<&str>,
+    ) -> Option<&'a SenderIdentity> {
+        use ty::sender_identity::SelectionType;
+
+        [
+            SelectionType::Email,
+            SelectionType::Domain,
+            SelectionType::Flow,
+        ]
+        .into_iter()
+        .find_map(|selection_type| {
+            identities.iter().find(|identity| {
+                identity.selection_type == selection_type && identity.matches(sender, flow_id)
+            })
+        })
+        .or_else(|| {
+            identities
+                .iter()
+                .find(|identity| identity.selection_type == SelectionType::Account)
+        })
+        .or_else(|| identities.iter().find(|identity| identity.account_fallback))
+    }
+}
+
+/// A message to run through the flows without sending it.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct SimulateMessage {
     /// Attachments. Only applicable when `messageType` = `EMAIL`.
+    #[serde(default)]
     pub attachments: Box<[Attachment]>,
     /// Extra data that will be available in templates.
     pub data: Object,
-    pub delivery_notification_type: ty::DeliveryNotificationType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delivery_notification_type: Option<ty::DeliveryNotificationType>,
     /// Freely configurable value that can be used to select a flow or one of its variants.
     /// Examples: invoice, previsit, ticket.
-    pub flow_selector: ty::String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub flow_selector: Option<ty::String>,
     /// From header address.
     /// Only applicable when `messageType` = `EMAIL`.
-    pub header_from_address: ty::String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub header_from_address: Option<ty::String>,
     /// From header name.
     /// Only applicable when `messageType` = `EMAIL`.
-    pub header_from_name: ty::String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub header_from_name: Option<ty::String>,
     /// To header address.
     /// Only applicable when `messageType` = `EMAIL`.
-    pub header_to_address: ty::String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub header_to_address: Option<ty::String>,
     /// To header name.
     /// Only applicable when `messageType` = `EMAIL`.
-    pub header_to_name: ty::String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub header_to_name: Option<ty::String>,
     /// Email headers.
+    #[serde(default)]
     pub headers: Box<[Header]>,
     /// Email HTML content.
     /// Only applicable when `messageType` = `EMAIL`.
-    pub html: ty::String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub html: Option<ty::String>,
     /// Valid values: `EMAIL`, `SMS` and `LETTER`.
     pub message_type: ty::MessageType,
     /// Complete email MIME message with headers.
     /// Only applicable when `messageType` = `EMAIL`.
-    pub mimedata: ty::Base64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mimedata: Option<ty::Base64>,
     /// Recipient email address or phone number.
     /// For email messages this cannot contain a display name.
     pub recipient_address: ty::String,
-    pub schedule_at: ty::Date,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schedule_at: Option<ty::Date>,
     /// Sender email address or phone number.
     /// For email messages this cannot contain a display name.
     pub sender_address: ty::String,
-    pub source_id: ty::String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_id: Option<ty::String>,
     /// Email subject.
-    pub subject: ty::String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subject: Option<ty::String>,
     /// Tags.
+    #[serde(default)]
     pub tags: Box<[ty::String]>,
     /// Text content.
-    pub text: ty::String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<ty::String>,
 }
 
-#[derive(Serialize, Deserialize)]
+impl From<SubmitMessage> for SimulateMessage {
+    fn from(message: SubmitMessage) -> Self {
+        Self {
+            attachments: message.attachments.unwrap_or_default(),
+            data: message.data,
+            delivery_notification_type: message.delivery_notification_type,
+            flow_selector: message.flow_selector,
+            header_from_address: Some(message.header_from_address),
+            header_from_name: Some(message.header_from_name),
+            header_to_address: message.header_to_address,
+            header_to_name: Some(message.header_to_name),
+            headers: message.headers,
+            html: message.html,
+            message_type: message.message_type,
+            mimedata: message.mimedata,
+            recipient_address: message.recipient_address,
+            schedule_at: message.schedule_at,
+            sender_address: message.sender_address,
+            source_id: None,
+            subject: Some(message.subject),
+            tags: message.tags.unwrap_or_default(),
+            text: message.text,
+        }
+    }
+}
+
+/// The message a simulated message would result in.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct SimulateMessageResult {
     /// Attachments, without the content.
     /// Only applicable when `messageType` = `EMAIL`.
+    #[serde(default)]
     pub attachments: Box<[Attachment]>,
     pub data: Object,
-    pub flow: ObjectDescription,
-    /// Archived message HTML.
-    pub html: String,
+    /// Flow the message would be processed in.
+    pub flow: Option<ObjectDescription>,
+    /// Message HTML.
+    pub html: Option<String>,
     pub message_type: ty::MessageType,
-    /// Archived message subject.
-    pub subject: ty::String,
-    /// Archived message text.
-    pub text: ty::String,
+    /// Message subject.
+    pub subject: Option<ty::String>,
+    /// Message text.
+    pub text: Option<ty::String>,
 }
 
 /// Information about a source system.
 /// A source system can submit messages to flowmailer.
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Source {
     /// Source description.
     pub description: ty::String,
     /// Email DSN messages will be sent to this address.
-    pub dsn_address: ty::String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dsn_address: Option<ty::String>,
     /// Disable sending DSN messages for this source.
-    pub dsn_disable: ty::String,
+    #[serde(default)]
+    pub dsn_disable: ty::Bool,
     /// Email feedback loop messages will be sent to this address.
-    pub feedback_loop_address: ty::String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub feedback_loop_address: Option<ty::String>,
     /// Human readable notifications for undelivered messages will be sent to this address.
-    pub human_readalbe_dsn_address: ty::String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub human_readable_dsn_address: Option<ty::String>,
     /// Enable sending human readable notifications for undelivered messages for this source.
+    #[serde(default)]
     pub human_readable_dsn_enable: ty::Bool,
     /// Source ID.
-    pub id: ty::String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<ty::String>,
     /// Date this source was last active.
-    pub last_active: ty::Date,
+    #[serde(default, skip_serializing)]
+    pub last_active: Option<ty::Date>,
     /// Maximum message size in bytes.
-    pub max_message_size: ty::Int,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_message_size: Option<ty::Int>,
     /// Message statistics summary for this source.
-    pub message_summary: MessageSummary,
+    #[serde(default, skip_serializing)]
+    pub message_summary: Option<MessageSummary>,
     /// Message statistics for this source.
-    pub statistics: Box<[Sample]>,
-    pub tis_required: ty::Bool,
+    #[serde(default, skip_serializing)]
+    pub statistics: Option<Box<[Sample]>>,
+    #[serde(default)]
+    pub tls_required: ty::Bool,
+    #[serde(rename = "type")]
     pub ty: ty::source::Type,
 }
 
+impl Source {
+    pub fn new(description: impl Into<String>, ty: ty::source::Type) -> Self {
+        Self {
+            description: description.into(),
+            dsn_address: None,
+            dsn_disable: false,
+            feedback_loop_address: None,
+            human_readable_dsn_address: None,
+            human_readable_dsn_enable: false,
+            id: None,
+            last_active: None,
+            max_message_size: None,
+            message_summary: None,
+            statistics: None,
+            tls_required: false,
+            ty,
+        }
+    }
+}
+
 /// An email or sms message that can be submitted to Flowmailer.
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct SubmitMessage {
     /// Attachments.
     /// Only applicable when `messageType` = `EMAIL`.
@@ -1141,30 +1728,36 @@ pub struct SubmitMessage {
 }
 
 /// A flowmailer content template.
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Template {
     /// Content-ID header (required for disposition related).
     /// Example: `<part1.DE1D8F7E.E51807FF@flowmailer.com>`.
     /// Only supported for custom content-types.
-    pub content_id: ty::String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_id: Option<ty::String>,
     /// Template content.
     pub data: ty::String,
     /// Decode Base64.
     /// Only supported for custom content-types.
-    pub decode_base64: ty::Bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decode_base64: Option<ty::Bool>,
     /// Template description.
     pub description: ty::String,
     /// Content-Disposition header for the attachment.
     /// Supported values include: `attachment`, `inline` and `related`.
     /// Special value `related` should be used for images referenced in the HTML.
     /// Only supported for custom content-types.
-    pub disposition: ty::template::Disposition,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disposition: Option<ty::template::Disposition>,
     /// Content filename.
     /// Only supported for custom content-types and `application/vnd.flowmailer.itext+pdf`.
-    pub filename: ty::String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filename: Option<ty::String>,
     /// Template ID.
-    pub id: ty::String,
-    /// Supported mime types: `text/plain`, `text/html` and `application/vnd.flowmailer.itext+pdf`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<ty::String>,
+    /// `text/plain`, `text/html`, `application/vnd.flowmailer.itext+pdf` or a custom content-type.
     pub mime_type: ty::template::MimeType,
     /// The only supported template engine is `freemarker-2.3.20`.
     pub template_engine: ty::template::TemplateEngine,
@@ -1,6 +1,9 @@
 use crate::client::Client;
 use crate::error::Result;
 use crate::request::{self, Response};
+use crate::ty;
+use serde::de::DeserializeOwned;
+use std::collections::VecDeque;
 use std::fmt::Debug;
 
 pub struct Id(u64);
@@ -11,9 +14,34 @@ impl Debug for Id {
     }
 }
 
+/// A single page of a paginated list call.
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Range of the returned items, from the `Content-Range` response header.
+    pub content_range: Option<ty::ContentRange>,
+}
+
+impl<T> Page<T> {
+    pub(crate) async fn from_response(response: Response) -> Result<Self>
+    where
+        T: DeserializeOwned,
+    {
+        let content_range = response.content_range()?;
+        Ok(Self {
+            items: response.json().await?,
+            content_range,
+        })
+    }
+
+    /// Returns the range of the next page, or None when this was the last page.
+    pub fn next_range(&self) -> Option<ty::ItemsRange> {
+        self.content_range.as_ref()?.next()
+    }
+}
+
 pub mod message {
     use super::*;
-    use crate::resources::SubmitMessage;
+    use crate::resources::{Message, SimulateMessage, SimulateMessageResult, SubmitMessage};
 
     const PATH: &str = "messages";
 
@@ -28,16 +56,1114 @@ pub mod message {
             .execute()
             .await
     }
+
+    /// Get a message by ID. Its events are only included when `add_events` is true.
+    pub async fn get(client: &Client, message_id: &str, add_events: bool) -> Result<Message> {
+        request::RequestBuilder::get(client, &subpath(&request::path_segment(message_id)), &[])?
+            .query(&[("addevents", add_events)])
+            .execute()
+            .await?
+            .json()
+            .await
+    }
+
+    /// Run a message through the flows without sending it, to preview the result.
+    pub async fn simulate(client: &Client, msg: SimulateMessage) -> Result<SimulateMessageResult> {
+        request::RequestBuilder::post(client, &subpath("simulate"), &[])?
+            .body(serde_json::to_string(&msg)?)
+            .execute()
+            .await?
+            .json()
+            .await
+    }
+
+    /// Send an email or sms message through an API source.
+    /// The source's credentials must be registered with [`Client::with_source`].
+    pub async fn submit_from(
+        client: &Client,
+        source_id: &str,
+        msg: SubmitMessage,
+    ) -> Result<Response> {
+        request::RequestBuilder::post(client, &subpath("submit"), &[])?
+            .source(source_id)?
+            .body(serde_json::to_string(&msg)?)
+            .execute()
+            .await
+    }
+}
+
+pub mod stats {
+    use super::*;
+    use crate::resources::DataSets;
+
+    /// Date range, interval and metrics for statistics calls.
+    pub struct Params {
+        date_range: ty::DateRange,
+        interval: Option<ty::Int>,
+        metrics: Vec<ty::Metric>,
+    }
+
+    impl Params {
+        pub fn new(date_range: ty::DateRange) -> Self {
+            Self {
+                date_range,
+                interval: None,
+                metrics: Vec::new(),
+            }
+        }
+
+        /// Time difference between samples.
+        pub fn interval(mut self, interval: ty::Int) -> Self {
+            self.interval = Some(interval);
+            self
+        }
+
+        /// Only return these metrics, all metrics are returned when none are set.
+        pub fn metrics(mut self, metrics: impl IntoIterator<Item = ty::Metric>) -> Self {
+            self.metrics.extend(metrics);
+            self
+        }
+    }
+
+    pub(crate) async fn get(client: &Client, path: &str, params: &Params) -> Result<DataSets> {
+        let date_range = params.date_range.to_string();
+        let interval = params.interval.map(|interval| interval.to_string());
+        let mut matrix_args = vec![("daterange", date_range.as_str())];
+        if let Some(interval) = &interval {
+            matrix_args.push(("interval", interval.as_str()));
+        }
+        let mut query = Vec::new();
+        if !params.metrics.is_empty() {
+            let metrics = params.metrics.iter().map(ty::Metric::as_str);
+            query.push(("metrics", metrics.collect::<Vec<_>>().join(",")));
+        }
+        request::RequestBuilder::get(client, path, &matrix_args)?
+            .query(&query)
+            .execute()
+            .await?
+            .json()
+            .await
+    }
+
+    /// Get time based statistics for the whole account.
+    pub async fn account(client: &Client, params: &Params) -> Result<DataSets> {
+        get(client, "stats", params).await
+    }
+
+    /// Get time based message statistics for the whole account.
+    pub async fn message_stats(client: &Client, params: &Params) -> Result<DataSets> {
+        get(client, "message_stats", params).await
+    }
 }
 
 pub mod flows {
     use super::*;
-    use crate::resources::Flow;
+    use crate::resources::{DataSets, Flow};
+
+    const PATH: &str = "flows";
+
+    fn subpath(path: &str) -> String {
+        format!("{PATH}/{path}")
+    }
 
-    pub async fn create(client: &Client, flow: Flow) -> Result<Response> {
-        request::RequestBuilder::post(client, "flows", &[])?
+    /// List flows of the account.
+    /// Message statistics and summaries are only included when `statistics` is true.
+    pub async fn list(client: &Client, statistics: bool) -> Result<Vec<Flow>> {
+        request::RequestBuilder::get(client, PATH, &[])?
+            .query(&[("statistics", statistics)])
+            .execute()
+            .await?
+            .json()
+            .await
+    }
+
+    /// Get a flow by ID.
+    /// Message statistics and summary are only included when `statistics` is true.
+    pub async fn get(client: &Client, flow_id: &str, statistics: bool) -> Result<Flow> {
+        request::RequestBuilder::get(client, &subpath(&request::path_segment(flow_id)), &[])?
+            .query(&[("statistics", statistics)])
+            .execute()
+            .await?
+            .json()
+            .await
+    }
+
+    /// Create a new flow and return it as stored by flowmailer.
+    pub async fn create(client: &Client, flow: Flow) -> Result<Flow> {
+        let id = request::RequestBuilder::post(client, PATH, &[])?
+            .body(serde_json::to_string(&flow)?)
+            .execute()
+            .await?
+            .location_id()?;
+        get(client, &id, false).await
+    }
+
+    /// Save a flow.
+    pub async fn update(client: &Client, flow_id: &str, flow: Flow) -> Result<Flow> {
+        request::RequestBuilder::put(client, &subpath(&request::path_segment(flow_id)), &[])?
             .body(serde_json::to_string(&flow)?)
             .execute()
+            .await?
+            .json()
+            .await
+    }
+
+    /// Delete a flow.
+    pub async fn delete(client: &Client, flow_id: &str) -> Result<()> {
+        request::RequestBuilder::delete(client, &subpath(&request::path_segment(flow_id)), &[])?
+            .execute()
+            .await?;
+        Ok(())
+    }
+
+    /// Get time based message statistics for a message flow.
+    pub async fn stats(client: &Client, flow_id: &str, params: &stats::Params) -> Result<DataSets> {
+        let path = subpath(&format!("{}/stats", request::path_segment(flow_id)));
+        stats::get(client, &path, params).await
+    }
+}
+
+pub mod sources {
+    use super::*;
+    use crate::resources::{DataSets, Source};
+
+    const PATH: &str = "sources";
+
+    fn subpath(path: &str) -> String {
+        format!("{PATH}/{path}")
+    }
+
+    /// List sources of the account.
+    /// Message statistics and summaries are only included when `statistics` is true.
+    pub async fn list(client: &Client, statistics: bool) -> Result<Vec<Source>> {
+        request::RequestBuilder::get(client, PATH, &[])?
+            .query(&[("statistics", statistics)])
+            .execute()
+            .await?
+            .json()
+            .await
+    }
+
+    /// Get a source by ID.
+    /// Message statistics and summary are only included when `statistics` is true.
+    pub async fn get(client: &Client, source_id: &str, statistics: bool) -> Result<Source> {
+        let path = subpath(&request::path_segment(source_id));
+        request::RequestBuilder::get(client, &path, &[])?
+            .query(&[("statistics", statistics)])
+            .execute()
+            .await?
+            .json()
+            .await
+    }
+
+    /// Create a new source and return it as stored by flowmailer.
+    pub async fn create(client: &Client, source: Source) -> Result<Source> {
+        let id = request::RequestBuilder::post(client, PATH, &[])?
+            .body(serde_json::to_string(&source)?)
+            .execute()
+            .await?
+            .location_id()?;
+        get(client, &id, false).await
+    }
+
+    /// Save a source.
+    pub async fn update(client: &Client, source_id: &str, source: Source) -> Result<Source> {
+        let path = subpath(&request::path_segment(source_id));
+        request::RequestBuilder::put(client, &path, &[])?
+            .body(serde_json::to_string(&source)?)
+            .execute()
+            .await?
+            .json()
+            .await
+    }
+
+    /// Delete a source.
+    pub async fn delete(client: &Client, source_id: &str) -> Result<()> {
+        let path = subpath(&request::path_segment(source_id));
+        request::RequestBuilder::delete(client, &path, &[])?
+            .execute()
+            .await?;
+        Ok(())
+    }
+
+    /// Get time based message statistics for a source.
+    pub async fn stats(
+        client: &Client,
+        source_id: &str,
+        params: &stats::Params,
+    ) -> Result<DataSets> {
+        let path = subpath(&format!("{}/stats", request::path_segment(source_id)));
+        stats::get(client, &path, params).await
+    }
+}
+
+pub mod source_credentials {
+    use super::*;
+    use crate::resources::Credentials;
+
+    fn path(source_id: &str) -> String {
+        format!("sources/{}/users", request::path_segment(source_id))
+    }
+
+    fn subpath(source_id: &str, credentials_id: &str) -> String {
+        format!(
+            "{}/{}",
+            path(source_id),
+            request::path_segment(credentials_id)
+        )
+    }
+
+    /// List the SMTP and SMPP credentials of a source.
+    pub async fn list(client: &Client, source_id: &str) -> Result<Vec<Credentials>> {
+        request::RequestBuilder::get(client, &path(source_id), &[])?
+            .execute()
+            .await?
+            .json()
+            .await
+    }
+
+    /// Get source credentials by ID.
+    pub async fn get(
+        client: &Client,
+        source_id: &str,
+        credentials_id: &str,
+    ) -> Result<Credentials> {
+        request::RequestBuilder::get(client, &subpath(source_id, credentials_id), &[])?
+            .execute()
+            .await?
+            .json()
+            .await
+    }
+
+    /// Create source credentials and return them as stored by flowmailer.
+    pub async fn create(
+        client: &Client,
+        source_id: &str,
+        credentials: Credentials,
+    ) -> Result<Credentials> {
+        let id = request::RequestBuilder::post(client, &path(source_id), &[])?
+            .body(serde_json::to_string(&credentials)?)
+            .execute()
+            .await?
+            .location_id()?;
+        get(client, source_id, &id).await
+    }
+
+    /// Save source credentials, e.g. to rotate the password.
+    pub async fn update(
+        client: &Client,
+        source_id: &str,
+        credentials_id: &str,
+        credentials: Credentials,
+    ) -> Result<Credentials> {
+        request::RequestBuilder::put(client, &subpath(source_id, credentials_id), &[])?
+            .body(serde_json::to_string(&credentials)?)
+            .execute()
+            .await?
+            .json()
+            .await
+    }
+
+    /// Delete source credentials.
+    pub async fn delete(client: &Client, source_id: &str, credentials_id: &str) -> Result<()> {
+        request::RequestBuilder::delete(client, &subpath(source_id, credentials_id), &[])?
+            .execute()
+            .await?;
+        Ok(())
+    }
+}
+
+pub mod message_events {
+    use super::*;
+    use crate::resources::MessageEvent;
+
+    const PATH: &str = "message_events";
+
+    /// Filters and options for listing message events.
+    #[derive(Default)]
+    pub struct Params {
+        date_range: Option<ty::DateRange>,
+        add_message_tags: bool,
+        sort_order: Option<ty::SortOrder>,
+    }
+
+    impl Params {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Only return events of messages submitted within this date range.
+        pub fn date_range(mut self, range: ty::DateRange) -> Self {
+            self.date_range = Some(range);
+            self
+        }
+
+        /// Include the tags of the message of each event.
+        pub fn add_message_tags(mut self, add: bool) -> Self {
+            self.add_message_tags = add;
+            self
+        }
+
+        pub fn sort_order(mut self, order: ty::SortOrder) -> Self {
+            self.sort_order = Some(order);
+            self
+        }
+    }
+
+    /// List message events.
+    pub async fn list(
+        client: &Client,
+        params: &Params,
+        range: &ty::ItemsRange,
+    ) -> Result<Page<MessageEvent>> {
+        let date_range = params.date_range.as_ref().map(ToString::to_string);
+        let matrix_args = match &date_range {
+            Some(date_range) => vec![("daterange", date_range.as_str())],
+            None => vec![],
+        };
+        let mut query = vec![("addmessagetags", params.add_message_tags.to_string())];
+        if let Some(order) = params.sort_order {
+            query.push(("sortorder", order.as_str().to_string()));
+        }
+        let response = request::RequestBuilder::get(client, PATH, &matrix_args)?
+            .query(&query)
+            .range(range)
+            .execute()
+            .await?;
+        Page::from_response(response).await
+    }
+}
+
+pub mod undelivered_messages {
+    use super::*;
+    use crate::resources::BouncedMessage;
+
+    const PATH: &str = "undeliveredmessages";
+
+    /// Filters and options for listing undelivered messages.
+    #[derive(Default)]
+    pub struct Params {
+        date_range: Option<ty::DateRange>,
+        received_from: Option<ty::Date>,
+        received_to: Option<ty::Date>,
+        add_events: bool,
+        add_headers: bool,
+        add_tags: bool,
+        sort_order: Option<ty::SortOrder>,
+    }
+
+    impl Params {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Only return messages submitted within this date range.
+        pub fn date_range(mut self, range: ty::DateRange) -> Self {
+            self.date_range = Some(range);
+            self
+        }
+
+        /// Only return messages bounced on or after this date.
+        pub fn received_from(mut self, date: ty::Date) -> Self {
+            self.received_from = Some(date);
+            self
+        }
+
+        /// Only return messages bounced before this date.
+        pub fn received_to(mut self, date: ty::Date) -> Self {
+            self.received_to = Some(date);
+            self
+        }
+
+        /// Include message events, required for [`BouncedMessage::classify`] to inspect events.
+        pub fn add_events(mut self, add: bool) -> Self {
+            self.add_events = add;
+            self
+        }
+
+        /// Include the email headers.
+        pub fn add_headers(mut self, add: bool) -> Self {
+            self.add_headers = add;
+            self
+        }
+
+        /// Include the message tags.
+        pub fn add_tags(mut self, add: bool) -> Self {
+            self.add_tags = add;
+            self
+        }
+
+        pub fn sort_order(mut self, order: ty::SortOrder) -> Self {
+            self.sort_order = Some(order);
+            self
+        }
+    }
+
+    /// List undeliverable messages.
+    pub async fn list(
+        client: &Client,
+        params: &Params,
+        range: &ty::ItemsRange,
+    ) -> Result<Page<BouncedMessage>> {
+        let date_range = params.date_range.as_ref().map(ToString::to_string);
+        let matrix_args = match &date_range {
+            Some(date_range) => vec![("daterange", date_range.as_str())],
+            None => vec![],
+        };
+        let mut query = vec![
+            ("addevents", params.add_events.to_string()),
+            ("addheaders", params.add_headers.to_string()),
+            ("addtags", params.add_tags.to_string()),
+        ];
+        if let Some(date) = &params.received_from {
+            query.push(("receivedFrom", date.to_string()));
+        }
+        if let Some(date) = &params.received_to {
+            query.push(("receivedTo", date.to_string()));
+        }
+        if let Some(order) = params.sort_order {
+            query.push(("sortorder", order.as_str().to_string()));
+        }
+        let response = request::RequestBuilder::get(client, PATH, &matrix_args)?
+            .query(&query)
+            .range(range)
+            .execute()
+            .await?;
+        Page::from_response(response).await
+    }
+}
+
+pub mod filters {
+    use super::*;
+    use crate::resources::Filter;
+
+    const PATH: &str = "filters";
+
+    fn subpath(path: &str) -> String {
+        format!("{PATH}/{path}")
+    }
+
+    /// Filters and options for listing suppressed recipients.
+    #[derive(Default)]
+    pub struct Params {
+        date_range: Option<ty::DateRange>,
+        sort_order: Option<ty::SortOrder>,
+    }
+
+    impl Params {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Only return filters added within this date range.
+        pub fn date_range(mut self, range: ty::DateRange) -> Self {
+            self.date_range = Some(range);
+            self
+        }
+
+        pub fn sort_order(mut self, order: ty::SortOrder) -> Self {
+            self.sort_order = Some(order);
+            self
+        }
+    }
+
+    /// List filtered recipients.
+    pub async fn list(
+        client: &Client,
+        params: &Params,
+        range: &ty::ItemsRange,
+    ) -> Result<Page<Filter>> {
+        let date_range = params.date_range.as_ref().map(ToString::to_string);
+        let matrix_args = match &date_range {
+            Some(date_range) => vec![("daterange", date_range.as_str())],
+            None => vec![],
+        };
+        let mut builder = request::RequestBuilder::get(client, PATH, &matrix_args)?.range(range);
+        if let Some(order) = params.sort_order {
+            builder = builder.query(&[("sortorder", order.as_str())]);
+        }
+        Page::from_response(builder.execute().await?).await
+    }
+
+    /// Get the filters for a recipient address.
+    /// Only filters for `message_type` are returned when it is set.
+    pub async fn get(
+        client: &Client,
+        address: &str,
+        message_type: Option<ty::MessageType>,
+    ) -> Result<Vec<Filter>> {
+        let recipient = super::recipient::get(client, address).await?;
+        Ok(recipient
+            .filters
+            .into_vec()
+            .into_iter()
+            .filter(|filter| match message_type {
+                Some(ty) => filter.message_type == ty,
+                None => true,
+            })
+            .collect())
+    }
+
+    /// Remove a recipient address from the filter list for the given message type.
+    pub async fn delete(
+        client: &Client,
+        address: &str,
+        message_type: ty::MessageType,
+    ) -> Result<()> {
+        let path = subpath(&format!(
+            "{}/{}",
+            request::path_segment(address),
+            message_type.as_str()
+        ));
+        request::RequestBuilder::delete(client, &path, &[])?
+            .execute()
+            .await?;
+        Ok(())
+    }
+
+    /// Export the complete filter list, fetching `page_size` filters per request.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # async fn run(client: &flowmailer::Client) -> flowmailer::Result<()> {
+    /// use flowmailer::rest_api::filters;
+    ///
+    /// let mut export = filters::export(client, filters::Params::new(), 100);
+    /// while let Some(filter) = export.next().await {
+    ///     println!("{}", filter?.address);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn export(client: &Client, params: Params, page_size: ty::Int) -> Export<'_> {
+        Export {
+            client,
+            params,
+            range: Some(0..page_size.max(1)),
+            buffer: VecDeque::new(),
+        }
+    }
+
+    /// Streaming export of the filter list, see [`export`].
+    pub struct Export<'a> {
+        client: &'a Client,
+        params: Params,
+        range: Option<ty::ItemsRange>,
+        buffer: VecDeque<Filter>,
+    }
+
+    impl Export<'_> {
+        /// Returns the next filter, fetching the next page when needed.
+        /// Returns None once every filter has been returned.
+        /// After an error, the next call retries the failed page.
+        pub async fn next(&mut self) -> Option<Result<Filter>> {
+            while self.buffer.is_empty() {
+                let range = self.range.take()?;
+                match list(self.client, &self.params, &range).await {
+                    Ok(page) => {
+                        // Without a content range, a full page means there may be more.
+                        let size = range.end - range.start;
+                        let full = page.items.len() as ty::Int == size;
+                        if !page.items.is_empty() {
+                            self.range = match page.content_range {
+                                Some(_) => page.next_range(),
+                                None => full.then(|| range.end..range.end + size),
+                            };
+                        }
+                        self.buffer.extend(page.items);
+                    }
+                    Err(err) => {
+                        // keep the range, so calling `next` again retries this page
+                        self.range = Some(range);
+                        return Some(Err(err));
+                    }
+                }
+            }
+            self.buffer.pop_front().map(Ok)
+        }
+    }
+}
+
+pub mod recipient {
+    use super::*;
+    use crate::resources::{Message, Recipient};
+
+    const PATH: &str = "recipient";
+
+    fn subpath(address: &str, path: &str) -> String {
+        format!("{PATH}/{}{path}", request::path_segment(address))
+    }
+
+    /// Filters and options for listing the messages of a recipient.
+    #[derive(Default)]
+    pub struct MessagesParams {
+        date_range: Option<ty::DateRange>,
+        add_events: bool,
+        add_headers: bool,
+        add_online_link: bool,
+        add_tags: bool,
+    }
+
+    impl MessagesParams {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Only return messages submitted within this date range.
+        pub fn date_range(mut self, range: ty::DateRange) -> Self {
+            self.date_range = Some(range);
+            self
+        }
+
+        /// Include message events.
+        pub fn add_events(mut self, add: bool) -> Self {
+            self.add_events = add;
+            self
+        }
+
+        /// Include the email headers.
+        pub fn add_headers(mut self, add: bool) -> Self {
+            self.add_headers = add;
+            self
+        }
+
+        /// Include the online link.
+        pub fn add_online_link(mut self, add: bool) -> Self {
+            self.add_online_link = add;
+            self
+        }
+
+        /// Include the message tags.
+        pub fn add_tags(mut self, add: bool) -> Self {
+            self.add_tags = add;
+            self
+        }
+    }
+
+    /// Get the filters and message statistics for a recipient email address or phone number.
+    pub async fn get(client: &Client, address: &str) -> Result<Recipient> {
+        request::RequestBuilder::get(client, &subpath(address, ""), &[])?
+            .execute()
+            .await?
+            .json()
+            .await
+    }
+
+    /// List the messages sent to a recipient.
+    pub async fn messages(
+        client: &Client,
+        address: &str,
+        params: &MessagesParams,
+        range: &ty::ItemsRange,
+    ) -> Result<Page<Message>> {
+        let date_range = params.date_range.as_ref().map(ToString::to_string);
+        let matrix_args = match &date_range {
+            Some(date_range) => vec![("daterange", date_range.as_str())],
+            None => vec![],
+        };
+        let response =
+            request::RequestBuilder::get(client, &subpath(address, "/messages"), &matrix_args)?
+                .query(&[
+                    ("addevents", params.add_events),
+                    ("addheaders", params.add_headers),
+                    ("addonlinelink", params.add_online_link),
+                    ("addtags", params.add_tags),
+                ])
+                .range(range)
+                .execute()
+                .await?;
+        Page::from_response(response).await
+    }
+}
+
+pub mod flow_rules {
+    use super::*;
+    use crate::resources::FlowRuleSimple;
+
+    const PATH: &str = "flow_rules";
+
+    /// Get the flow selection rules, in the order flowmailer evaluates them.
+    pub async fn get(client: &Client) -> Result<Vec<FlowRuleSimple>> {
+        request::RequestBuilder::get(client, PATH, &[])?
+            .execute()
+            .await?
+            .json()
+            .await
+    }
+
+    /// Replace all flow selection rules.
+    pub async fn replace(client: &Client, rules: &[FlowRuleSimple]) -> Result<()> {
+        request::RequestBuilder::put(client, PATH, &[])?
+            .body(serde_json::to_string(rules)?)
+            .execute()
+            .await?;
+        Ok(())
+    }
+}
+
+pub mod event_flows {
+    use super::*;
+    use crate::resources::EventFlow;
+
+    const PATH: &str = "event_flows";
+
+    fn subpath(path: &str) -> String {
+        format!("{PATH}/{path}")
+    }
+
+    /// List event flows of the account.
+    pub async fn list(client: &Client) -> Result<Vec<EventFlow>> {
+        request::RequestBuilder::get(client, PATH, &[])?
+            .execute()
+            .await?
+            .json()
+            .await
+    }
+
+    /// Get an event flow by ID.
+    pub async fn get(client: &Client, event_flow_id: &str) -> Result<EventFlow> {
+        let path = subpath(&request::path_segment(event_flow_id));
+        request::RequestBuilder::get(client, &path, &[])?
+            .execute()
+            .await?
+            .json()
+            .await
+    }
+
+    /// Create a new event flow and return it as stored by flowmailer.
+    pub async fn create(client: &Client, event_flow: EventFlow) -> Result<EventFlow> {
+        let id = request::RequestBuilder::post(client, PATH, &[])?
+            .body(serde_json::to_string(&event_flow)?)
+            .execute()
+            .await?
+            .location_id()?;
+        get(client, &id).await
+    }
+
+    /// Save an event flow.
+    pub async fn update(
+        client: &Client,
+        event_flow_id: &str,
+        event_flow: EventFlow,
+    ) -> Result<EventFlow> {
+        let path = subpath(&request::path_segment(event_flow_id));
+        request::RequestBuilder::put(client, &path, &[])?
+            .body(serde_json::to_string(&event_flow)?)
+            .execute()
+            .await?
+            .json()
+            .await
+    }
+
+    /// Delete an event flow.
+    pub async fn delete(client: &Client, event_flow_id: &str) -> Result<()> {
+        let path = subpath(&request::path_segment(event_flow_id));
+        request::RequestBuilder::delete(client, &path, &[])?
+            .execute()
+            .await?;
+        Ok(())
+    }
+}
+
+pub mod event_flow_rules {
+    use super::*;
+    use crate::resources::EventFlowRuleSimple;
+
+    const PATH: &str = "event_flow_rules";
+
+    /// Get the event flow selection rules.
+    pub async fn get(client: &Client) -> Result<Vec<EventFlowRuleSimple>> {
+        request::RequestBuilder::get(client, PATH, &[])?
+            .execute()
+            .await?
+            .json()
+            .await
+    }
+
+    /// Replace all event flow selection rules.
+    pub async fn replace(client: &Client, rules: &[EventFlowRuleSimple]) -> Result<()> {
+        request::RequestBuilder::put(client, PATH, &[])?
+            .body(serde_json::to_string(rules)?)
+            .execute()
+            .await?;
+        Ok(())
+    }
+}
+
+pub mod flow_templates {
+    use super::*;
+    use crate::resources::{Flow, FlowStep, FlowTemplate};
+
+    const PATH: &str = "flow_templates";
+
+    fn subpath(path: &str) -> String {
+        format!("{PATH}/{path}")
+    }
+
+    /// List flow templates of the account.
+    pub async fn list(client: &Client) -> Result<Vec<FlowTemplate>> {
+        request::RequestBuilder::get(client, PATH, &[])?
+            .execute()
+            .await?
+            .json()
+            .await
+    }
+
+    /// Get a flow template by ID.
+    pub async fn get(client: &Client, flow_template_id: &str) -> Result<FlowTemplate> {
+        let path = subpath(&request::path_segment(flow_template_id));
+        request::RequestBuilder::get(client, &path, &[])?
+            .execute()
+            .await?
+            .json()
+            .await
+    }
+
+    /// Create a new flow template and return it as stored by flowmailer.
+    pub async fn create(client: &Client, flow_template: FlowTemplate) -> Result<FlowTemplate> {
+        let id = request::RequestBuilder::post(client, PATH, &[])?
+            .body(serde_json::to_string(&flow_template)?)
+            .execute()
+            .await?
+            .location_id()?;
+        get(client, &id).await
+    }
+
+    /// Save a flow template.
+    pub async fn update(
+        client: &Client,
+        flow_template_id: &str,
+        flow_template: FlowTemplate,
+    ) -> Result<FlowTemplate> {
+        let path = subpath(&request::path_segment(flow_template_id));
+        request::RequestBuilder::put(client, &path, &[])?
+            .body(serde_json::to_string(&flow_template)?)
+            .execute()
+            .await?
+            .json()
+            .await
+    }
+
+    /// Delete a flow template.
+    pub async fn delete(client: &Client, flow_template_id: &str) -> Result<()> {
+        let path = subpath(&request::path_segment(flow_template_id));
+        request::RequestBuilder::delete(client, &path, &[])?
+            .execute()
+            .await?;
+        Ok(())
+    }
+
+    /// Create a new flow from a flow template.
+    /// Each step in `overrides` replaces the template step of the same type, or is appended.
+    pub async fn create_flow(
+        client: &Client,
+        flow_template_id: &str,
+        description: impl Into<String>,
+        overrides: impl IntoIterator<Item = FlowStep>,
+    ) -> Result<Flow> {
+        let flow = get(client, flow_template_id).await?.to_flow(description)?;
+        let flow = overrides.into_iter().fold(flow, Flow::with_step);
+        super::flows::create(client, flow).await
+    }
+}
+
+pub mod templates {
+    use super::*;
+    use crate::resources::Template;
+
+    const PATH: &str = "templates";
+
+    fn subpath(path: &str) -> String {
+        format!("{PATH}/{path}")
+    }
+
+    /// List content templates of the account.
+    pub async fn list(client: &Client) -> Result<Vec<Template>> {
+        request::RequestBuilder::get(client, PATH, &[])?
+            .execute()
+            .await?
+            .json()
+            .await
+    }
+
+    /// Get a content template by ID.
+    pub async fn get(client: &Client, template_id: &str) -> Result<Template> {
+        let path = subpath(&request::path_segment(template_id));
+        request::RequestBuilder::get(client, &path, &[])?
+            .execute()
+            .await?
+            .json()
+            .await
+    }
+
+    /// Create a new content template and return it as stored by flowmailer.
+    pub async fn create(client: &Client, template: Template) -> Result<Template> {
+        let id = request::RequestBuilder::post(client, PATH, &[])?
+            .body(serde_json::to_string(&template)?)
+            .execute()
+            .await?
+            .location_id()?;
+        get(client, &id).await
+    }
+
+    /// Save a content template.
+    pub async fn update(
+        client: &Client,
+        template_id: &str,
+        template: Template,
+    ) -> Result<Template> {
+        let path = subpath(&request::path_segment(template_id));
+        request::RequestBuilder::put(client, &path, &[])?
+            .body(serde_json::to_string(&template)?)
+            .execute()
+            .await?
+            .json()
+            .await
+    }
+
+    /// Delete a content template.
+    pub async fn delete(client: &Client, template_id: &str) -> Result<()> {
+        let path = subpath(&request::path_segment(template_id));
+        request::RequestBuilder::delete(client, &path, &[])?
+            .execute()
+            .await?;
+        Ok(())
+    }
+}
+
+pub mod sender_domains {
+    use super::*;
+    use crate::resources::SenderDomain;
+
+    const PATH: &str = "sender_domains";
+
+    fn subpath(path: &str) -> String {
+        format!("{PATH}/{path}")
+    }
+
+    /// List sender domains of the account.
+    pub async fn list(client: &Client) -> Result<Vec<SenderDomain>> {
+        request::RequestBuilder::get(client, PATH, &[])?
+            .execute()
+            .await?
+            .json()
             .await
     }
+
+    /// Get a sender domain by ID, including the DNS records that should exist.
+    /// The DNS records are validated when `validate` is true.
+    pub async fn get(client: &Client, domain_id: &str, validate: bool) -> Result<SenderDomain> {
+        let path = subpath(&request::path_segment(domain_id));
+        request::RequestBuilder::get(client, &path, &[])?
+            .query(&[("validate", validate)])
+            .execute()
+            .await?
+            .json()
+            .await
+    }
+
+    /// Get a sender domain by domain name, including the DNS records that should exist.
+    /// The DNS records are validated when `validate` is true.
+    pub async fn by_domain(client: &Client, domain: &str, validate: bool) -> Result<SenderDomain> {
+        let path = subpath(&format!("by_domain/{}", request::path_segment(domain)));
+        request::RequestBuilder::get(client, &path, &[])?
+            .query(&[("validate", validate)])
+            .execute()
+            .await?
+            .json()
+            .await
+    }
+
+    /// Create a sender domain and return it with the DNS records that should exist.
+    pub async fn create(client: &Client, sender_domain: SenderDomain) -> Result<SenderDomain> {
+        let id = request::RequestBuilder::post(client, PATH, &[])?
+            .body(serde_json::to_string(&sender_domain)?)
+            .execute()
+            .await?
+            .location_id()?;
+        get(client, &id, false).await
+    }
+
+    /// Validate the DNS records of a sender domain without saving it.
+    pub async fn validate(client: &Client, sender_domain: &SenderDomain) -> Result<SenderDomain> {
+        request::RequestBuilder::post(client, &subpath("validate"), &[])?
+            .body(serde_json::to_string(sender_domain)?)
+            .execute()
+            .await?
+            .json()
+            .await
+    }
+
+    /// Delete a sender domain.
+    pub async fn delete(client: &Client, domain_id: &str) -> Result<()> {
+        let path = subpath(&request::path_segment(domain_id));
+        request::RequestBuilder::delete(client, &path, &[])?
+            .execute()
+            .await?;
+        Ok(())
+    }
+}
+
+pub mod sender_identities {
+    use super::*;
+    use crate::resources::SenderIdentity;
+
+    const PATH: &str = "sender_identities";
+
+    fn subpath(path: &str) -> String {
+        format!("{PATH}/{path}")
+    }
+
+    /// List sender identities of the account.
+    pub async fn list(client: &Client) -> Result<Vec<SenderIdentity>> {
+        request::RequestBuilder::get(client, PATH, &[])?
+            .execute()
+            .await?
+            .json()
+            .await
+    }
+
+    /// Get a sender identity by ID.
+    pub async fn get(client: &Client, identity_id: &str) -> Result<SenderIdentity> {
+        let path = subpath(&request::path_segment(identity_id));
+        request::RequestBuilder::get(client, &path, &[])?
+            .execute()
+            .await?
+            .json()
+            .await
+    }
+
+    /// Create a sender identity and return it as stored by flowmailer.
+    pub async fn create(client: &Client, identity: SenderIdentity) -> Result<SenderIdentity> {
+        let id = request::RequestBuilder::post(client, PATH, &[])?
+            .body(serde_json::to_string(&identity)?)
+            .execute()
+            .await?
+            .location_id()?;
+        get(client, &id).await
+    }
+
+    /// Save a sender identity.
+    pub async fn update(
+        client: &Client,
+        identity_id: &str,
+        identity: SenderIdentity,
+    ) -> Result<SenderIdentity> {
+        let path = subpath(&request::path_segment(identity_id));
+        request::RequestBuilder::put(client, &path, &[])?
+            .body(serde_json::to_string(&identity)?)
+            .execute()
+            .await?
+            .json()
+            .await
+    }
+
+    /// Delete a sender identity.
+    pub async fn delete(client: &Client, identity_id: &str) -> Result<()> {
+        let path = subpath(&request::path_segment(identity_id));
+        request::RequestBuilder::delete(client, &path, &[])?
+            .execute()
+            .await?;
+        Ok(())
+    }
 }
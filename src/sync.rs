@@ -0,0 +1,474 @@
+//! Declarative management of templates, flows and flow rules.
+//!
+//! A [`DesiredState`] describes the templates, flows and flow rules an account should have.
+//! [`plan`] compares it with the account and returns the creates, updates and deletes needed,
+//! which [`Plan::apply`] then performs.
+//!
+//! Templates and flows are matched by description, since IDs differ between accounts, so
+//! descriptions must be unique within the desired state.
+//! To reference a template or flow of the desired state, use `ref:<description>` as ID in
+//! template steps and flow rules. It is replaced by the ID of that resource in the account,
+//! and [`plan`] fails when neither the account nor the desired state has that resource.
+
+use crate::error::{self, Error, Result};
+use crate::resources::{Flow, FlowRuleSimple, FlowStepKind, Template};
+use crate::{rest_api, Client};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Display;
+use std::path::Path;
+
+const REF_PREFIX: &str = "ref:";
+
+/// Templates, flows and flow rules an account should have.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DesiredState {
+    #[serde(default)]
+    pub templates: Vec<Template>,
+    #[serde(default)]
+    pub flows: Vec<Flow>,
+    /// Flow rules in evaluation order.
+    /// The flow rules of the account are left as they are when this is not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub flow_rules: Option<Vec<FlowRuleSimple>>,
+}
+
+impl DesiredState {
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    #[cfg(feature = "yaml")]
+    pub fn from_yaml(yaml: &str) -> Result<Self> {
+        serde_yaml::from_str(yaml).map_err(|err| Error::new(error::Kind::Parse(err.to_string())))
+    }
+
+    /// Loads the desired state from a `.json` file, or a `.yaml`/`.yml` file with the `yaml` feature.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path).map_err(|err| {
+            Error::new(error::Kind::Other(format!(
+                "failed to read '{}': {err}",
+                path.display()
+            )))
+        })?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            #[cfg(feature = "yaml")]
+            Some("yaml" | "yml") => Self::from_yaml(&content),
+            _ => Self::from_json(&content),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Create,
+    Update,
+    Delete,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResourceKind {
+    Template,
+    Flow,
+    FlowRules,
+}
+
+/// A single planned change.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Change {
+    pub action: Action,
+    pub kind: ResourceKind,
+    /// Description of the template or flow.
+    pub name: String,
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let action = match self.action {
+            Action::Create => "+ create",
+            Action::Update => "~ update",
+            Action::Delete => "- delete",
+        };
+        match self.kind {
+            ResourceKind::Template => write!(f, "{action} template '{}'", self.name),
+            ResourceKind::Flow => write!(f, "{action} flow '{}'", self.name),
+            ResourceKind::FlowRules => write!(f, "{action} flow rules"),
+        }
+    }
+}
+
+/// Changes needed to bring an account to the desired state, in the order they are applied.
+pub struct Plan {
+    pub changes: Vec<Change>,
+    templates: HashMap<String, Template>,
+    flows: HashMap<String, Flow>,
+    flow_rules: Option<Vec<FlowRuleSimple>>,
+    template_ids: HashMap<String, String>,
+    flow_ids: HashMap<String, String>,
+}
+
+impl Display for Plan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.changes.is_empty() {
+            return writeln!(f, "No changes.");
+        }
+        for change in &self.changes {
+            writeln!(f, "{change}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Compares the desired state with the account.
+/// Templates and flows that are not in the desired state are only deleted when `prune` is true.
+pub async fn plan(client: &Client, desired: DesiredState, prune: bool) -> Result<Plan> {
+    let current_templates = rest_api::templates::list(client).await?;
+    let current_flows = rest_api::flows::list(client, false).await?;
+    let current_rules = match desired.flow_rules {
+        Some(_) => Some(rest_api::flow_rules::get(client).await?),
+        None => None,
+    };
+    diff(
+        desired,
+        current_templates,
+        current_flows,
+        current_rules,
+        prune,
+    )
+}
+
+/// Creates the plan from already fetched account resources.
+/// Fails when the desired state has several templates or flows with the same description.
+pub(crate) fn diff(
+    desired: DesiredState,
+    current_templates: Vec<Template>,
+    current_flows: Vec<Flow>,
+    current_rules: Option<Vec<FlowRuleSimple>>,
+    prune: bool,
+) -> Result<Plan> {
+    check_unique(
+        "template",
+        desired
+            .templates
+            .iter()
+            .map(|template| &template.description),
+    )?;
+    check_unique("flow", desired.flows.iter().map(|flow| &flow.description))?;
+    let template_ids = ids_by_description(
+        current_templates
+            .iter()
+            .map(|template| (&template.description, &template.id)),
+    );
+    let flow_ids = ids_by_description(
+        current_flows
+            .iter()
+            .map(|flow| (&flow.description, &flow.id)),
+    );
+    // Resources created by this plan get their ID when it is applied.
+    let planned_template_ids = with_planned_ids(
+        &template_ids,
+        desired
+            .templates
+            .iter()
+            .map(|template| &template.description),
+    );
+    let planned_flow_ids = with_planned_ids(
+        &flow_ids,
+        desired.flows.iter().map(|flow| &flow.description),
+    );
+    let mut changes = Vec::new();
+
+    let current_templates: BTreeMap<_, _> = current_templates
+        .into_iter()
+        .map(|template| (template.description.clone(), template))
+        .collect();
+    for template in &desired.templates {
+        let action = match current_templates.get(&template.description) {
+            None => Some(Action::Create),
+            Some(current) if !is_subset(&normalized(template)?, &normalized(current)?) => {
+                Some(Action::Update)
+            }
+            Some(_) => None,
+        };
+        if let Some(action) = action {
+            changes.push(Change {
+                action,
+                kind: ResourceKind::Template,
+                name: template.description.clone(),
+            });
+        }
+    }
+
+    let current_flows: BTreeMap<_, _> = current_flows
+        .into_iter()
+        .map(|flow| (flow.description.clone(), flow))
+        .collect();
+    let mut flows = HashMap::new();
+    for flow in desired.flows {
+        let resolved = resolve_flow(flow.clone(), &planned_template_ids)?;
+        let action = match current_flows.get(&flow.description) {
+            None => Some(Action::Create),
+            Some(current) if !is_subset(&normalized(&resolved)?, &normalized(current)?) => {
+                Some(Action::Update)
+            }
+            Some(_) => None,
+        };
+        if let Some(action) = action {
+            changes.push(Change {
+                action,
+                kind: ResourceKind::Flow,
+                name: flow.description.clone(),
+            });
+        }
+        flows.insert(flow.description.clone(), flow);
+    }
+
+    if let (Some(rules), Some(current)) = (&desired.flow_rules, &current_rules) {
+        let resolved = rules
+            .iter()
+            .map(|rule| resolve_rule(rule.clone(), &planned_flow_ids))
+            .collect::<Result<Vec<_>>>()?;
+        if !is_subset(&normalized(&resolved)?, &normalized(current)?) {
+            changes.push(Change {
+                action: Action::Update,
+                kind: ResourceKind::FlowRules,
+                name: String::new(),
+            });
+        }
+    }
+
+    if prune {
+        for description in current_flows.keys() {
+            if !flows.contains_key(description) {
+                changes.push(Change {
+                    action: Action::Delete,
+                    kind: ResourceKind::Flow,
+                    name: description.clone(),
+                });
+            }
+        }
+        for description in current_templates.keys() {
+            if !desired
+                .templates
+                .iter()
+                .any(|template| &template.description == description)
+            {
+                changes.push(Change {
+                    action: Action::Delete,
+                    kind: ResourceKind::Template,
+                    name: description.clone(),
+                });
+            }
+        }
+    }
+
+    Ok(Plan {
+        changes,
+        templates: desired
+            .templates
+            .into_iter()
+            .map(|template| (template.description.clone(), template))
+            .collect(),
+        flows,
+        flow_rules: desired.flow_rules,
+        template_ids,
+        flow_ids,
+    })
+}
+
+impl Plan {
+    /// Applies the planned changes and returns them.
+    /// Nothing is changed in the account when `dry_run` is true.
+    pub async fn apply(mut self, client: &Client, dry_run: bool) -> Result<Vec<Change>> {
+        if dry_run {
+            return Ok(self.changes);
+        }
+        for change in &self.changes {
+            match (change.kind, change.action) {
+                (ResourceKind::Template, Action::Delete) => {
+                    let id = self.template_id(&change.name)?;
+                    rest_api::templates::delete(client, &id).await?;
+                }
+                (ResourceKind::Template, action) => {
+                    let template = self
+                        .templates
+                        .remove(&change.name)
+                        .ok_or_else(|| missing("template", &change.name))?;
+                    let saved = match action {
+                        Action::Create => rest_api::templates::create(client, template).await?,
+                        _ => {
+                            let id = self.template_id(&change.name)?;
+                            rest_api::templates::update(client, &id, template).await?
+                        }
+                    };
+                    if let Some(id) = saved.id {
+                        self.template_ids.insert(change.name.clone(), id);
+                    }
+                }
+                (ResourceKind::Flow, Action::Delete) => {
+                    let id = self.flow_id(&change.name)?;
+                    rest_api::flows::delete(client, &id).await?;
+                }
+                (ResourceKind::Flow, action) => {
+                    let flow = self
+                        .flows
+                        .remove(&change.name)
+                        .ok_or_else(|| missing("flow", &change.name))?;
+                    let flow = resolve_flow(flow, &self.template_ids)?;
+                    let saved = match action {
+                        Action::Create => rest_api::flows::create(client, flow).await?,
+                        _ => {
+                            let id = self.flow_id(&change.name)?;
+                            rest_api::flows::update(client, &id, flow).await?
+                        }
+                    };
+                    if let Some(id) = saved.id {
+                        self.flow_ids.insert(change.name.clone(), id);
+                    }
+                }
+                (ResourceKind::FlowRules, _) => {
+                    let rules = self
+                        .flow_rules
+                        .take()
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|rule| resolve_rule(rule, &self.flow_ids))
+                        .collect::<Result<Vec<_>>>()?;
+                    rest_api::flow_rules::replace(client, &rules).await?;
+                }
+            }
+        }
+        Ok(self.changes)
+    }
+
+    fn template_id(&self, description: &str) -> Result<String> {
+        self.template_ids
+            .get(description)
+            .cloned()
+            .ok_or_else(|| missing("template", description))
+    }
+
+    fn flow_id(&self, description: &str) -> Result<String> {
+        self.flow_ids
+            .get(description)
+            .cloned()
+            .ok_or_else(|| missing("flow", description))
+    }
+}
+
+fn missing(kind: &str, description: &str) -> Error {
+    Error::new(error::Kind::Other(format!(
+        "no {kind} with description '{description}'"
+    )))
+}
+
+fn check_unique<'a>(kind: &str, descriptions: impl Iterator<Item = &'a String>) -> Result<()> {
+    let mut seen = HashSet::new();
+    for description in descriptions {
+        if !seen.insert(description) {
+            return Err(Error::new(error::Kind::Other(format!(
+                "desired state has more than one {kind} with description '{description}'"
+            ))));
+        }
+    }
+    Ok(())
+}
+
+fn ids_by_description<'a>(
+    resources: impl Iterator<Item = (&'a String, &'a Option<String>)>,
+) -> HashMap<String, String> {
+    resources
+        .filter_map(|(description, id)| Some((description.clone(), id.clone()?)))
+        .collect()
+}
+
+/// Adds `ref:<description>` as ID for the `descriptions` that have no ID yet,
+/// so references to resources the plan creates resolve to themselves.
+fn with_planned_ids<'a>(
+    ids: &HashMap<String, String>,
+    descriptions: impl Iterator<Item = &'a String>,
+) -> HashMap<String, String> {
+    let mut planned = ids.clone();
+    for description in descriptions {
+        planned
+            .entry(description.clone())
+            .or_insert_with(|| format!("{REF_PREFIX}{description}"));
+    }
+    planned
+}
+
+/// Replaces a `ref:<description>` ID with the ID of that resource.
+/// Fails when there is no `kind` with that description.
+fn resolve_id(id: &mut String, ids: &HashMap<String, String>, kind: &str) -> Result<()> {
+    if let Some(description) = id.strip_prefix(REF_PREFIX) {
+        *id = ids.get(description).cloned().ok_or_else(|| {
+            Error::new(error::Kind::Other(format!(
+                "'{id}' does not refer to a {kind} in the account or the desired state"
+            )))
+        })?;
+    }
+    Ok(())
+}
+
+fn resolve_flow(mut flow: Flow, template_ids: &HashMap<String, String>) -> Result<Flow> {
+    for step in flow.steps.iter_mut() {
+        if let FlowStepKind::Template { template } = &mut step.kind {
+            resolve_id(&mut template.id, template_ids, "template")?;
+        }
+    }
+    Ok(flow)
+}
+
+fn resolve_rule(
+    mut rule: FlowRuleSimple,
+    flow_ids: &HashMap<String, String>,
+) -> Result<FlowRuleSimple> {
+    resolve_id(&mut rule.flow_id, flow_ids, "flow")?;
+    Ok(rule)
+}
+
+/// Serializes resources without their own IDs and statistics, which are managed by flowmailer.
+/// IDs that reference other resources, such as the template of a template step, are kept.
+fn normalized(resource: &impl Serialize) -> Result<serde_json::Value> {
+    fn strip(value: &mut serde_json::Value) {
+        if let Some(map) = value.as_object_mut() {
+            map.remove("id");
+            map.remove("statistics");
+            map.remove("messageSummary");
+            if let Some(steps) = map.get_mut("steps").and_then(|steps| steps.as_array_mut()) {
+                for step in steps.iter_mut().filter_map(|step| step.as_object_mut()) {
+                    step.remove("id");
+                }
+            }
+        }
+    }
+    let mut value = serde_json::to_value(resource)?;
+    match &mut value {
+        serde_json::Value::Array(values) => values.iter_mut().for_each(strip),
+        value => strip(value),
+    }
+    Ok(value)
+}
+
+/// Returns true when every field set in `desired` has the same value in `current`.
+/// Fields only present in `current`, such as defaults filled in by flowmailer, are ignored.
+fn is_subset(desired: &serde_json::Value, current: &serde_json::Value) -> bool {
+    use serde_json::Value;
+    match (desired, current) {
+        (Value::Object(desired), Value::Object(current)) => desired.iter().all(|(key, value)| {
+            current
+                .get(key)
+                .is_some_and(|current| is_subset(value, current))
+        }),
+        (Value::Array(desired), Value::Array(current)) => {
+            desired.len() == current.len()
+                && desired
+                    .iter()
+                    .zip(current)
+                    .all(|(desired, current)| is_subset(desired, current))
+        }
+        (desired, current) => desired == current,
+    }
+}
@@ -1,36 +1,100 @@
-use crate::resources::{FlowStep, ObjectDescription};
-use crate::ty::flow_step;
-use crate::{resources, rest_api, Client};
+use crate::error::{self, Error, Result};
+use crate::ty::template::{MimeType, TemplateEngine};
+use crate::{freemarker, rest_api, Client};
+use serde::Serialize;
 
-pub struct Template {}
+pub use crate::resources::Template;
+
+/// Ties the data of a transactional mail to the flow that renders it.
+///
+/// # Example
+/// ```
+/// use flowmailer::{MailAddress, MailBuilder, TypedTemplate};
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct PasswordReset {
+///     name: String,
+///     code: String,
+/// }
+///
+/// impl TypedTemplate for PasswordReset {
+///     const FLOW_SELECTOR: &'static str = "password-reset";
+/// }
+///
+/// let mail = MailBuilder::new_typed(
+///     MailAddress::new("noreply@example.com"),
+///     MailAddress::new("customer@example.com"),
+///     PasswordReset {
+///         name: "Ann".to_string(),
+///         code: "123456".to_string(),
+///     },
+/// );
+/// ```
+pub trait TypedTemplate: Serialize {
+    /// Flow selector of the flow that renders this data.
+    const FLOW_SELECTOR: &'static str;
+
+    /// Renders `template` locally with this data.
+    /// Fails when the template uses a variable this data does not provide.
+    fn render(&self, template: &Template) -> Result<String> {
+        template.render(self)
+    }
+}
 
 impl Template {
-    pub async fn test(client: &Client) {
-        let flow = resources::Flow {
-            description: "description".to_string(),
+    /// Creates a new, unsaved content template.
+    pub fn new(
+        description: impl Into<String>,
+        mime_type: MimeType,
+        data: impl Into<String>,
+    ) -> Self {
+        Self {
+            content_id: None,
+            data: data.into(),
+            decode_base64: None,
+            description: description.into(),
+            disposition: None,
+            filename: None,
             id: None,
-            message_summary: None,
-            statistics: None,
-            steps: Box::new([FlowStep::template(ObjectDescription {
-                description: None,
-                id: "68489".to_string(),
-            })]),
-            template_id: "1".to_string(),
-        };
-        println!(
-            "{}",
-            crate::request::RequestBuilder::get(client, "flow_templates", &[])
-                .expect("failed to create request builder")
-                .execute()
-                .await
-                .expect("failed test 0")
-                .into_inner()
-                .text()
-                .await
-                .unwrap()
-        );
-        rest_api::flows::create(client, flow)
-            .await
-            .expect("failed test");
+            mime_type,
+            template_engine: TemplateEngine::Freemarker,
+        }
+    }
+
+    /// Renders this template locally against the same data passed to `MailBuilder::set_data`.
+    /// Only a subset of FreeMarker is supported, see [`freemarker`].
+    pub fn render(&self, data: impl Serialize) -> Result<String> {
+        freemarker::render(&self.data, &serde_json::to_value(data)?)
+    }
+
+    /// List content templates of the account.
+    pub async fn list(client: &Client) -> Result<Vec<Self>> {
+        rest_api::templates::list(client).await
+    }
+
+    /// Get a content template by ID.
+    pub async fn get(client: &Client, template_id: &str) -> Result<Self> {
+        rest_api::templates::get(client, template_id).await
+    }
+
+    /// Create this template and return it as stored by flowmailer.
+    pub async fn create(self, client: &Client) -> Result<Self> {
+        rest_api::templates::create(client, self).await
+    }
+
+    /// Save this template, which must have been created before.
+    pub async fn update(self, client: &Client) -> Result<Self> {
+        let id = self.id.clone().ok_or_else(|| {
+            Error::new(error::Kind::Other(
+                "cannot update a template without ID".to_string(),
+            ))
+        })?;
+        rest_api::templates::update(client, &id, self).await
+    }
+
+    /// Delete a content template by ID.
+    pub async fn delete(client: &Client, template_id: &str) -> Result<()> {
+        rest_api::templates::delete(client, template_id).await
     }
 }
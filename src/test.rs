@@ -1 +1,1798 @@
+use crate::resources::BouncedMessage;
+use crate::ty;
 
+/// Deserializes `base` with the fields of `extra` added or replaced.
+fn merged<T: serde::de::DeserializeOwned>(
+    mut base: serde_json::Value,
+    extra: serde_json::Value,
+) -> T {
+    base.as_object_mut()
+        .unwrap()
+        .extend(extra.as_object().unwrap().clone());
+    serde_json::from_value(base).expect("valid resource json")
+}
+
+/// Transport that records requests and answers them with queued responses.
+/// Token requests are answered automatically; other requests get `201 Created` once the queue is empty.
+#[derive(Clone, Default)]
+struct Recording {
+    requests: std::sync::Arc<std::sync::Mutex<Vec<crate::transport::HttpRequest>>>,
+    responses: std::sync::Arc<std::sync::Mutex<Vec<crate::transport::HttpResponse>>>,
+}
+
+impl Recording {
+    fn respond(self, response: crate::transport::HttpResponse) -> Self {
+        self.responses.lock().unwrap().push(response);
+        self
+    }
+
+    fn requests(&self) -> Vec<crate::transport::HttpRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    fn client(&self) -> crate::Client {
+        use crate::auth::{AccountId, ClientId, ClientSecret};
+        let auth = crate::Auth::new(
+            ClientSecret::new("secret".to_string()),
+            ClientId::new("id".to_string()),
+            AccountId::from("1234"),
+        );
+        crate::Client::with_transport(auth, self.clone())
+    }
+}
+
+impl crate::transport::HttpTransport for Recording {
+    fn send(
+        &self,
+        request: crate::transport::HttpRequest,
+    ) -> crate::transport::TransportFuture<'_> {
+        use crate::transport::HttpResponse;
+        let response = if request.url.ends_with("/oauth/token") {
+            HttpResponse::new(200).body(r#"{"access_token":"abc","expires_in":60}"#)
+        } else {
+            self.requests.lock().unwrap().push(request);
+            let mut responses = self.responses.lock().unwrap();
+            match responses.is_empty() {
+                true => HttpResponse::new(201),
+                false => responses.remove(0),
+            }
+        };
+        Box::pin(std::future::ready(Ok(response)))
+    }
+}
+
+fn bounced_message(json: serde_json::Value) -> BouncedMessage {
+    let message = serde_json::json!({
+        "id": "1",
+        "messageType": "EMAIL",
+        "recipientAddress": "bounce@example.com",
+        "status": "BOUNCED",
+    });
+    merged(message, json)
+}
+
+fn submit_message(json: serde_json::Value) -> crate::resources::SubmitMessage {
+    let message = serde_json::json!({
+        "data": {},
+        "headerFromAddress": "noreply@example.com",
+        "headerFromName": "",
+        "headerToName": "",
+        "headers": [],
+        "messageType": "EMAIL",
+        "recipientAddress": "customer@example.com",
+        "senderAddress": "noreply@example.com",
+        "subject": "",
+    });
+    merged(message, json)
+}
+
+#[test]
+fn content_range_parses_and_pages() {
+    let range: ty::ContentRange = "items 0-10/25".parse().unwrap();
+    assert_eq!(range.range, 0..10);
+    assert_eq!(range.total, Some(25));
+    assert_eq!(range.next(), Some(10..20));
+
+    let last: ty::ContentRange = "items 20-25/25".parse().unwrap();
+    assert_eq!(last.next(), None);
+
+    assert!("bytes 0-10/25".parse::<ty::ContentRange>().is_err());
+}
+
+#[test]
+fn bounce_classified_from_bounce_type() {
+    let message = bounced_message(serde_json::json!({ "bounceType": "HARD" }));
+    assert_eq!(message.classify(), Some(ty::BounceType::Hard));
+}
+
+#[test]
+fn bounce_classified_from_events() {
+    let message = bounced_message(serde_json::json!({
+        "events": [
+            {
+                "id": "e2",
+                "messageId": "1",
+                "received": "2023-01-02T00:00:00Z",
+                "type": "BOUNCE",
+                "snippet": "452 4.2.2 Mailbox full",
+            },
+            {
+                "id": "e1",
+                "messageId": "1",
+                "received": "2023-01-01T00:00:00Z",
+                "type": "SUBMITTED",
+            },
+        ],
+    }));
+    assert_eq!(message.classify(), Some(ty::BounceType::Soft));
+}
+
+#[test]
+fn bounce_classified_from_snippet() {
+    let message = bounced_message(serde_json::json!({
+        "bounceSnippet": "smtp; 550 5.1.1 user unknown",
+    }));
+    assert_eq!(message.classify(), Some(ty::BounceType::Hard));
+
+    let message = bounced_message(serde_json::json!({ "bounceSnippet": "no code here" }));
+    assert_eq!(message.classify(), None);
+}
+
+#[test]
+fn path_segment_encodes_addresses() {
+    assert_eq!(
+        crate::request::path_segment("john+news@example.com"),
+        "john%2Bnews@example.com"
+    );
+    assert_eq!(crate::request::path_segment("a/b c"), "a%2Fb%20c");
+}
+
+#[test]
+fn recipient_deserializes_summary_and_filters() {
+    let recipient: crate::resources::Recipient = serde_json::from_value(serde_json::json!({
+        "address": "customer@example.com",
+        "filters": [{
+            "id": "f1",
+            "address": "customer@example.com",
+            "messageType": "EMAIL",
+            "reason": "complaint",
+        }],
+        "messageSummary": {
+            "averageDeliverTimeMillis": 120,
+            "clicked": 3,
+            "delivered": 10,
+            "opened": 7,
+            "processed": 10,
+            "sent": 10,
+            "uniqueClicked": 2,
+            "uniqueOpened": 5,
+        },
+    }))
+    .unwrap();
+    assert_eq!(recipient.filters[0].message_type, ty::MessageType::Email);
+    assert_eq!(recipient.message_summary.unique_opened, 5);
+    assert_eq!(recipient.message_summary.avarage_deliver_time_millis, 120);
+}
+
+#[test]
+fn data_sets_lookup_by_metric() {
+    let sets: crate::resources::DataSets = serde_json::from_value(serde_json::json!({
+        "list": [
+            {
+                "name": "sent",
+                "samples": [
+                    { "timestamp": "2023-01-01T00:00:00Z", "value": 4 },
+                    { "timestamp": "2023-01-02T00:00:00Z", "value": 6 },
+                ],
+            },
+        ],
+    }))
+    .unwrap();
+    assert_eq!(sets.get(ty::Metric::Sent).map(|set| set.total()), Some(10));
+    assert!(sets.get(ty::Metric::Opened).is_none());
+}
+
+#[test]
+fn flow_steps_keep_wire_format() {
+    use crate::resources::{FlowStep, FlowStepKind, FlowStepSetHeader, ObjectDescription};
+
+    let step = FlowStep::template(ObjectDescription {
+        description: None,
+        id: "68489".to_string(),
+    });
+    assert_eq!(
+        serde_json::to_value(&step).unwrap(),
+        serde_json::json!({ "type": "template", "template": { "id": "68489" } })
+    );
+
+    let step = FlowStep::add_header(FlowStepSetHeader {
+        header_name: "X-Tenant".to_string(),
+        header_value: "acme".to_string(),
+    })
+    .enabled_expression("${tenant??}");
+    assert_eq!(
+        serde_json::to_value(&step).unwrap(),
+        serde_json::json!({
+            "type": "addHeader",
+            "enabledExpression": "${tenant??}",
+            "setHeader": { "headerName": "X-Tenant", "headerValue": "acme" },
+        })
+    );
+
+    let step: FlowStep = serde_json::from_value(serde_json::json!({
+        "id": "s1",
+        "type": "qamail",
+        "to": "qa@example.com",
+        "divisor": 10,
+    }))
+    .unwrap();
+    assert!(matches!(step.ty(), ty::flow_step::Type::QAmail));
+    assert!(matches!(
+        step.kind,
+        FlowStepKind::QAmail { divisor: Some(10), ref to } if to == "qa@example.com"
+    ));
+}
+
+#[test]
+fn flow_rules_route_first_matching_rule() {
+    use crate::resources::{FlowRuleSimple, SubmitMessage};
+
+    let rules: Vec<FlowRuleSimple> = serde_json::from_value(serde_json::json!([
+        {
+            "flowId": "vip-invoice",
+            "flowSelector": "invoice",
+            "dataExpressions": [{ "expression": "${customer.tier}", "value": "gold" }],
+        },
+        { "flowId": "invoice", "flowSelector": "invoice" },
+        { "flowId": "tenant", "headers": [{ "name": "X-Tenant", "value": "acme" }] },
+        { "flowId": "default" },
+    ]))
+    .unwrap();
+    let route = |message: &SubmitMessage| {
+        FlowRuleSimple::route(&rules, message, None).map(|rule| rule.flow_id.as_str())
+    };
+
+    let gold = submit_message(serde_json::json!({
+        "flowSelector": "invoice",
+        "data": { "customer": { "tier": "gold" } },
+    }));
+    assert_eq!(route(&gold), Some("vip-invoice"));
+    let regular = submit_message(serde_json::json!({ "flowSelector": "invoice" }));
+    assert_eq!(route(&regular), Some("invoice"));
+    let tenant = submit_message(serde_json::json!({
+        "headers": [{ "name": "x-tenant", "value": "acme" }],
+    }));
+    assert_eq!(route(&tenant), Some("tenant"));
+    assert_eq!(
+        route(&submit_message(serde_json::json!({}))),
+        Some("default")
+    );
+}
+
+#[test]
+fn flow_from_template_overrides_steps() {
+    use crate::resources::{FlowStep, FlowTemplate};
+
+    let template: FlowTemplate = serde_json::from_value(serde_json::json!({
+        "id": "parent",
+        "description": "Transactional",
+        "steps": [
+            { "id": "s1", "type": "subject", "subjectTemplate": "Hello" },
+            { "id": "s2", "type": "archive", "archive": { "onlineLink": true, "retention": "P1M" } },
+            { "id": "s3", "type": "qamail", "to": "first@example.com" },
+        ],
+    }))
+    .unwrap();
+    let copied = template.to_flow("Tenant A").unwrap();
+    assert!(copied.steps.iter().all(|step| step.id.is_none()));
+
+    let flow = copied
+        .with_step(FlowStep::subject("Hello from tenant A"))
+        .with_step_at(3, FlowStep::qamail("qa@example.com"));
+
+    assert_eq!(flow.template_id, "parent");
+    let types: Vec<_> = flow.steps.iter().map(FlowStep::ty).collect();
+    assert_eq!(
+        types,
+        [
+            ty::flow_step::Type::Subject,
+            ty::flow_step::Type::Archive,
+            ty::flow_step::Type::QAmail,
+            ty::flow_step::Type::QAmail
+        ]
+    );
+    assert!(matches!(
+        &flow.steps[0].kind,
+        crate::resources::FlowStepKind::Subject { subject_template } if subject_template == "Hello from tenant A"
+    ));
+}
+
+#[tokio::test]
+async fn flow_template_create_flow_posts_overridden_steps() {
+    use crate::resources::{FlowStep, FlowTemplate};
+    use crate::rest_api::flow_templates;
+    use crate::transport::{HttpResponse, Method};
+
+    let template = serde_json::json!({
+        "id": "parent",
+        "description": "Transactional",
+        "steps": [
+            { "id": "s1", "type": "subject", "subjectTemplate": "Hello" },
+            { "id": "s2", "type": "qamail", "to": "qa@example.com" },
+        ],
+    });
+    let transport = Recording::default()
+        .respond(HttpResponse::new(200).body(template.to_string()))
+        .respond(
+            HttpResponse::new(201).header("Location", "https://api.flowmailer.net/1234/flows/77"),
+        )
+        .respond(HttpResponse::new(200).body(
+            serde_json::json!({ "id": "77", "description": "Tenant A", "templateId": "parent", "steps": [] })
+                .to_string(),
+        ));
+    let client = transport.client();
+
+    let flow = flow_templates::create_flow(
+        &client,
+        "parent",
+        "Tenant A",
+        [FlowStep::subject("Hello from tenant A")],
+    )
+    .await
+    .unwrap();
+    assert_eq!(flow.id.as_deref(), Some("77"));
+
+    let requests = transport.requests();
+    assert_eq!(
+        requests[0].url,
+        "https://api.flowmailer.net/1234/flow_templates/parent"
+    );
+    assert_eq!(requests[1].method, Method::Post);
+    assert_eq!(requests[1].url, "https://api.flowmailer.net/1234/flows");
+    let body: serde_json::Value =
+        serde_json::from_slice(requests[1].body.as_deref().unwrap()).unwrap();
+    assert_eq!(
+        body,
+        serde_json::json!({
+            "description": "Tenant A",
+            "templateId": "parent",
+            "steps": [
+                { "type": "subject", "subjectTemplate": "Hello from tenant A" },
+                { "type": "qamail", "to": "qa@example.com" },
+            ],
+        })
+    );
+
+    let unsaved: FlowTemplate = merged(template, serde_json::json!({ "id": null }));
+    assert!(unsaved.to_flow("Tenant B").is_err());
+}
+
+#[test]
+fn sync_plans_creates_updates_and_deletes() {
+    use crate::resources::{Flow, FlowRuleSimple};
+    use crate::sync::{self, Action, Change, DesiredState, ResourceKind};
+    use crate::Template;
+
+    let desired = DesiredState::from_json(
+        &serde_json::json!({
+            "templates": [
+                {
+                    "description": "Welcome",
+                    "data": "<p>Hi ${name}</p>",
+                    "mimeType": "text/html",
+                    "templateEngine": "freemarker-2.3.20",
+                },
+                {
+                    "description": "Receipt",
+                    "data": "Thanks",
+                    "mimeType": "text/plain",
+                    "templateEngine": "freemarker-2.3.20",
+                },
+            ],
+            "flows": [
+                {
+                    "description": "Welcome flow",
+                    "templateId": "1",
+                    "steps": [{ "type": "template", "template": { "id": "ref:Welcome" } }],
+                },
+            ],
+            "flowRules": [{ "flowId": "ref:Welcome flow", "flowSelector": "welcome" }],
+        })
+        .to_string(),
+    )
+    .unwrap();
+    let current_templates: Vec<Template> = serde_json::from_value(serde_json::json!([
+        {
+            "id": "t1",
+            "description": "Welcome",
+            "data": "<p>Hi ${name}</p>",
+            "decodeBase64": false,
+            "mimeType": "text/html",
+            "templateEngine": "freemarker-2.3.20",
+        },
+        {
+            "id": "t2",
+            "description": "Old",
+            "data": "",
+            "mimeType": "text/plain",
+            "templateEngine": "freemarker-2.3.20",
+        },
+    ]))
+    .unwrap();
+    let current_flows: Vec<Flow> = serde_json::from_value(serde_json::json!([
+        {
+            "id": "f1",
+            "description": "Welcome flow",
+            "templateId": "1",
+            "steps": [{ "id": "s1", "type": "template", "template": { "id": "t1" } }],
+        },
+    ]))
+    .unwrap();
+    let current_rules: Vec<FlowRuleSimple> =
+        serde_json::from_value(serde_json::json!([{ "flowId": "f1", "flowSelector": "welcome" }]))
+            .unwrap();
+
+    let plan = sync::diff(
+        desired,
+        current_templates,
+        current_flows,
+        Some(current_rules),
+        true,
+    )
+    .unwrap();
+    assert_eq!(
+        plan.changes,
+        [
+            Change {
+                action: Action::Create,
+                kind: ResourceKind::Template,
+                name: "Receipt".to_string(),
+            },
+            Change {
+                action: Action::Delete,
+                kind: ResourceKind::Template,
+                name: "Old".to_string(),
+            },
+        ]
+    );
+    assert_eq!(
+        plan.to_string(),
+        "+ create template 'Receipt'\n- delete template 'Old'\n"
+    );
+}
+
+#[test]
+fn sync_rejects_duplicate_descriptions() {
+    use crate::sync::{self, DesiredState};
+
+    let flow = serde_json::json!({ "description": "Welcome flow", "templateId": "1", "steps": [] });
+    let desired =
+        DesiredState::from_json(&serde_json::json!({ "flows": [flow, flow] }).to_string()).unwrap();
+    let err = sync::diff(desired, vec![], vec![], None, false)
+        .err()
+        .unwrap();
+    assert!(err.to_string().contains("'Welcome flow'"));
+}
+
+#[tokio::test]
+async fn sync_apply_creates_in_order_and_resolves_refs() {
+    use crate::sync::{self, DesiredState};
+    use crate::transport::{HttpResponse, Method};
+
+    let desired = serde_json::json!({
+        "templates": [{
+            "description": "Welcome",
+            "data": "Hi",
+            "mimeType": "text/plain",
+            "templateEngine": "freemarker-2.3.20",
+        }],
+        "flows": [{
+            "description": "Welcome flow",
+            "templateId": "1",
+            "steps": [{ "type": "template", "template": { "id": "ref:Welcome" } }],
+        }],
+        "flowRules": [{ "flowId": "ref:Welcome flow", "flowSelector": "welcome" }],
+    });
+    let stored = |value: serde_json::Value| HttpResponse::new(200).body(value.to_string());
+    let created = |path: &str| {
+        HttpResponse::new(201).header(
+            "Location",
+            format!("https://api.flowmailer.net/1234/{path}"),
+        )
+    };
+    let transport = Recording::default()
+        .respond(HttpResponse::new(200).body("[]"))
+        .respond(HttpResponse::new(200).body("[]"))
+        .respond(HttpResponse::new(200).body("[]"))
+        .respond(created("templates/t1"))
+        .respond(stored(merged(
+            desired["templates"][0].clone(),
+            serde_json::json!({ "id": "t1" }),
+        )))
+        .respond(created("flows/f1"))
+        .respond(stored(serde_json::json!({
+            "id": "f1", "description": "Welcome flow", "templateId": "1", "steps": [],
+        })))
+        .respond(HttpResponse::new(200));
+    let client = transport.client();
+
+    let desired = DesiredState::from_json(&desired.to_string()).unwrap();
+    let plan = sync::plan(&client, desired, false).await.unwrap();
+    assert_eq!(
+        plan.to_string(),
+        "+ create template 'Welcome'\n+ create flow 'Welcome flow'\n~ update flow rules\n"
+    );
+    plan.apply(&client, false).await.unwrap();
+
+    let requests = transport.requests();
+    let calls: Vec<_> = requests
+        .iter()
+        .map(|request| {
+            (
+                request.method,
+                request
+                    .url
+                    .trim_start_matches("https://api.flowmailer.net/1234/"),
+            )
+        })
+        .collect();
+    assert_eq!(
+        calls,
+        [
+            (Method::Get, "templates"),
+            (Method::Get, "flows?statistics=false"),
+            (Method::Get, "flow_rules"),
+            (Method::Post, "templates"),
+            (Method::Get, "templates/t1"),
+            (Method::Post, "flows"),
+            (Method::Get, "flows/f1?statistics=false"),
+            (Method::Put, "flow_rules"),
+        ]
+    );
+    let body = |index: usize| -> serde_json::Value {
+        serde_json::from_slice(requests[index].body.as_deref().unwrap()).unwrap()
+    };
+    assert_eq!(body(5)["steps"][0]["template"]["id"], "t1");
+    assert_eq!(body(7)[0]["flowId"], "f1");
+}
+
+#[test]
+fn sync_rejects_unknown_refs() {
+    use crate::sync::{self, DesiredState};
+
+    let desired = DesiredState::from_json(
+        &serde_json::json!({
+            "flows": [{
+                "description": "Welcome flow",
+                "templateId": "1",
+                "steps": [{ "type": "template", "template": { "id": "ref:Missing" } }],
+            }],
+        })
+        .to_string(),
+    )
+    .unwrap();
+    let err = sync::diff(desired, vec![], vec![], None, false)
+        .err()
+        .unwrap();
+    assert!(err.to_string().contains("'ref:Missing'"));
+}
+
+#[test]
+fn freemarker_renders_common_subset() {
+    use crate::freemarker::render;
+
+    let data = serde_json::json!({
+        "name": "<Ann's>",
+        "email": "ann@example.com",
+        "vip": true,
+        "total": 42,
+        "lines": [{ "sku": "a1", "qty": 2 }, { "sku": "b2", "qty": 1 }],
+    });
+    let template = r#"<#-- order mail -->Hi ${name?html}!
+<#if vip>VIP<#elseif total gt 100>Big<#else>Regular</#if>
+<#list lines as line>${line_index + 1}. ${line.sku?upper_case} x${line.qty}<#if line_has_next>, </#if></#list>
+${nickname!"friend"} ${nickname???c} <#if (total > 10) && !(lines?size == 0)>ok</#if>
+<#assign greeting = "Hello " + name>${greeting?length}
+<#if !lines?has_content>none</#if>?to=${email?url}"#;
+    assert_eq!(
+        render(template, &data).unwrap(),
+        "Hi &lt;Ann's&gt;!\nVIP\n1. A1 x2, 2. B2 x1\nfriend false ok\n13\n?to=ann%40example.com"
+    );
+}
+
+#[test]
+fn freemarker_rejects_2_3_23_syntax() {
+    use crate::error::Kind;
+    use crate::freemarker::render;
+
+    let data = serde_json::json!({ "lines": [1, 2] });
+    for template in [
+        "<#list lines as line>${line}<#sep>, </#list>",
+        "<#list lines as line>${line}<#else>none</#list>",
+        "<#list lines as line>${line?counter}</#list>",
+        "<#list lines as line><#if line?has_next>,</#if></#list>",
+    ] {
+        let err = render(template, &data).unwrap_err();
+        assert!(matches!(err.kind, Kind::Render(_)), "{template}");
+        assert!(err.to_string().contains("2.3.23"), "{template}");
+    }
+}
+
+#[test]
+fn freemarker_reports_missing_values() {
+    let err = crate::freemarker::render("Hi ${customer.name}", &serde_json::json!({})).unwrap_err();
+    assert_eq!(err.to_string(), "render: 'customer.name' is missing");
+    assert!(crate::freemarker::render("<#if x>", &serde_json::json!({ "x": true })).is_err());
+}
+
+#[test]
+fn typed_template_checks_data_against_template() {
+    use crate::ty::template::MimeType;
+    use crate::{Template, TypedTemplate};
+
+    #[derive(serde::Serialize)]
+    struct Welcome {
+        name: String,
+    }
+
+    impl TypedTemplate for Welcome {
+        const FLOW_SELECTOR: &'static str = "welcome";
+    }
+
+    let data = Welcome {
+        name: "Ann".to_string(),
+    };
+    let template = Template::new("Welcome", MimeType::TextPlain, "Hi ${name}");
+    assert_eq!(data.render(&template).unwrap(), "Hi Ann");
+
+    let outdated = Template::new("Welcome", MimeType::TextPlain, "Hi ${firstName}");
+    assert!(data.render(&outdated).is_err());
+}
+
+#[tokio::test]
+async fn templates_keep_custom_mime_types() {
+    use crate::transport::HttpResponse;
+    use crate::ty::template::MimeType;
+    use crate::Template;
+
+    let logo = serde_json::json!({
+        "id": "9",
+        "description": "logo",
+        "mimeType": "image/png",
+        "templateEngine": "freemarker-2.3.20",
+        "data": "iVBORw0KGgo=",
+        "decodeBase64": true,
+        "contentId": "<logo@example.com>",
+        "disposition": "related",
+    });
+    let transport = Recording::default()
+        .respond(
+            HttpResponse::new(201)
+                .header("Location", "https://api.flowmailer.net/1234/templates/9"),
+        )
+        .respond(HttpResponse::new(200).body(logo.to_string()));
+    let client = transport.client();
+
+    let mut template = Template::new("logo", MimeType::from("image/png"), "iVBORw0KGgo=");
+    template.decode_base64 = Some(true);
+    let created = template.create(&client).await.unwrap();
+    assert_eq!(created.mime_type, MimeType::Custom("image/png".to_string()));
+    assert_eq!(created.id.as_deref(), Some("9"));
+
+    let requests = transport.requests();
+    let body: serde_json::Value =
+        serde_json::from_slice(requests[0].body.as_deref().unwrap()).unwrap();
+    assert_eq!(body["mimeType"], "image/png");
+    assert_eq!(
+        serde_json::to_value(&created).unwrap()["mimeType"],
+        "image/png"
+    );
+
+    let html: Template = merged(logo, serde_json::json!({ "mimeType": "text/html" }));
+    assert_eq!(html.mime_type, MimeType::TextHtml);
+}
+
+#[test]
+fn sender_domain_reports_failing_records() {
+    let domain: crate::resources::SenderDomain = serde_json::from_value(serde_json::json!({
+        "id": "d1",
+        "senderDomain": "example.com",
+        "returnPathDomain": "bounce.example.com",
+        "webDomain": "links.example.com",
+        "dnsRecords": [
+            { "name": "example.com", "type": "TXT", "value": "v=spf1 ...", "status": "OK" },
+            {
+                "name": "fm1._domainkey.example.com",
+                "type": "CNAME",
+                "value": "fm1.dkim.flowmailer.net",
+                "status": "ERROR",
+                "errorMessages": ["CNAME record not found"],
+            },
+            { "name": "links.example.com", "type": "CNAME", "value": "web.flowmailer.net" },
+        ],
+    }))
+    .unwrap();
+    let failing: Vec<_> = domain
+        .failing_records()
+        .map(|record| record.name.as_str())
+        .collect();
+    assert_eq!(failing, ["fm1._domainkey.example.com"]);
+    assert_eq!(
+        domain.dns_records[1].status,
+        Some(ty::dns_record::Status::Error)
+    );
+}
+
+#[tokio::test]
+async fn dns_verify_reports_mismatches_per_record() {
+    use crate::dns::{self, StaticResolver};
+    use crate::resources::DnsRecord;
+    use ty::dns_record::Status;
+
+    let records: Vec<DnsRecord> = serde_json::from_value(serde_json::json!([
+        { "name": "example.com", "type": "TXT", "value": "<code>v=spf1 include:flowmailer.net ~all</code>" },
+        { "name": "fm1._domainkey.example.com", "type": "CNAME", "value": "fm1.dkim.flowmailer.net" },
+        { "name": "bounce.example.com", "type": "CNAME", "value": "return.flowmailer.net" },
+        { "name": "links.example.com", "type": "CNAME", "value": "web.flowmailer.net" },
+    ]))
+    .unwrap();
+    let resolver = StaticResolver::new()
+        .record(
+            "example.com",
+            "TXT",
+            "v=spf1 include:_spf.google.com include:flowmailer.net ~all",
+        )
+        .record(
+            "fm1._domainkey.example.com.",
+            "CNAME",
+            "FM1.dkim.flowmailer.net.",
+        )
+        .record("bounce.example.com", "CNAME", "mail.example.com.");
+
+    let verified = dns::verify(&resolver, &records).await;
+    let statuses: Vec<_> = verified.iter().map(|record| record.status).collect();
+    assert_eq!(
+        statuses,
+        [
+            Some(Status::Ok),
+            Some(Status::Ok),
+            Some(Status::Error),
+            Some(Status::Error)
+        ]
+    );
+    assert_eq!(
+        &*verified[2].error_messages,
+        ["CNAME record points to mail.example.com, expected return.flowmailer.net"]
+    );
+    assert_eq!(
+        &*verified[3].error_messages,
+        ["no CNAME record found for links.example.com"]
+    );
+}
+
+#[test]
+fn sender_identity_resolution_prefers_most_specific() {
+    use crate::resources::SenderIdentity;
+    use ty::sender_identity::SelectionType;
+
+    let identities: Vec<SenderIdentity> = serde_json::from_value(serde_json::json!([
+        { "description": "default", "selectionType": "account", "selectionValue": "1234",
+          "senderEmail": "info@example.com", "senderName": "Example",
+          "returnPathDomain": "", "webDomain": "" },
+        { "description": "invoices", "selectionType": "flow", "selectionValue": "42",
+          "senderEmail": "billing@example.com", "senderName": "Billing",
+          "returnPathDomain": "", "webDomain": "" },
+        { "description": "brand", "selectionType": "domain", "selectionValue": "brand.com",
+          "senderEmail": "hello@brand.com", "senderName": "Brand",
+          "returnPathDomain": "", "webDomain": "" },
+    ]))
+    .unwrap();
+    assert_eq!(identities[2].selection_type, SelectionType::Domain);
+
+    let resolve = |sender, flow_id| {
+        SenderIdentity::resolve(&identities, sender, flow_id).map(|id| id.description.as_str())
+    };
+    assert_eq!(resolve("noreply@Brand.com", Some("42")), Some("brand"));
+    assert_eq!(resolve("noreply@example.com", Some("42")), Some("invoices"));
+    assert_eq!(resolve("noreply@example.com", None), Some("default"));
+
+    let fallback: SenderIdentity = merged(
+        serde_json::to_value(&identities[1]).unwrap(),
+        serde_json::json!({ "description": "fallback", "accountFallback": true }),
+    );
+    for identities in [
+        vec![fallback.clone(), identities[0].clone()],
+        vec![identities[0].clone(), fallback.clone()],
+    ] {
+        let resolved = SenderIdentity::resolve(&identities, "noreply@example.com", None);
+        assert_eq!(resolved.unwrap().description, "default");
+    }
+    let resolved = SenderIdentity::resolve(
+        std::slice::from_ref(&fallback),
+        "noreply@example.com",
+        Some("7"),
+    );
+    assert_eq!(resolved.unwrap().description, "fallback");
+}
+
+#[test]
+fn source_wire_format() {
+    use crate::resources::Source;
+
+    let source: Source = serde_json::from_value(serde_json::json!({
+        "id": "7", "description": "billing service", "type": "API",
+        "tlsRequired": true, "humanReadableDsnAddress": "ops@example.com",
+        "lastActive": "2024-01-01T00:00:00.000Z", "statistics": []
+    }))
+    .unwrap();
+    assert_eq!(source.ty, ty::source::Type::Api);
+    assert!(source.tls_required);
+    assert_eq!(
+        source.human_readable_dsn_address.as_deref(),
+        Some("ops@example.com")
+    );
+
+    let json = serde_json::to_value(Source::new("billing service", ty::source::Type::Api)).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!({
+            "description": "billing service", "dsnDisable": false,
+            "humanReadableDsnEnable": false, "tlsRequired": false, "type": "API"
+        })
+    );
+}
+
+#[test]
+fn source_credentials_redact_password() {
+    use crate::resources::Credentials;
+
+    let credentials = Credentials::new(
+        "legacy smtp",
+        ty::credentials::Protocol::SmtpIpOnly,
+        "legacy",
+        "hunter2",
+    );
+    let debug = format!("{credentials:?}");
+    assert!(!debug.contains("hunter2"));
+    assert!(debug.contains("[redacted]"));
+
+    let json = serde_json::to_value(&credentials).unwrap();
+    assert_eq!(json["protocol"], "SMTP_IPONLY");
+    assert_eq!(json["password"], "hunter2");
+
+    let stored: Credentials = serde_json::from_value(json).unwrap();
+    assert_eq!(stored.password.unwrap().expose_secret(), "hunter2");
+}
+
+#[test]
+fn secrets_are_redacted() {
+    use crate::auth::{AccessToken, Auth};
+
+    let token = AccessToken::new("eyJhbGciOi".to_string());
+    assert_eq!(token.to_string(), "[redacted]");
+    assert!(!format!("{token:?}").contains("eyJhbGciOi"));
+    assert_eq!(token.expose_secret(), "eyJhbGciOi");
+
+    let auth = Auth::from_contents(
+        "# test account\nFLOWMAILER_CLIENT_ID=id\nFLOWMAILER_CLIENT_SECRET=\"s3cret\"\n\nFLOWMAILER_ACCOUNT_ID=1234\n",
+    )
+    .unwrap();
+    assert_eq!(auth.account_id().as_str(), "1234");
+    assert!(Auth::from_contents("FLOWMAILER_CLIENT_ID=id").is_err());
+}
+
+#[tokio::test]
+async fn caching_token_provider_reuses_until_invalidated() {
+    use crate::auth::{AccessToken, CachingTokenProvider, TokenFuture, TokenProvider};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    struct Counting(AtomicUsize, Duration);
+
+    impl TokenProvider for Counting {
+        fn token(&self) -> TokenFuture<'_> {
+            let n = self.0.fetch_add(1, Ordering::SeqCst);
+            let token = AccessToken::new(format!("token-{n}")).expires_in(self.1);
+            Box::pin(std::future::ready(Ok(token)))
+        }
+    }
+
+    let tokens =
+        CachingTokenProvider::new(Counting(AtomicUsize::new(0), Duration::from_secs(3600)));
+    assert_eq!(tokens.token().await.unwrap().expose_secret(), "token-0");
+    assert_eq!(tokens.token().await.unwrap().expose_secret(), "token-0");
+    tokens.invalidate();
+    assert_eq!(tokens.token().await.unwrap().expose_secret(), "token-1");
+
+    // tokens that expire within the refresh margin are not reused
+    let tokens = CachingTokenProvider::new(Counting(AtomicUsize::new(0), Duration::from_secs(5)));
+    tokens.token().await.unwrap();
+    assert_eq!(tokens.token().await.unwrap().expose_secret(), "token-1");
+}
+
+#[tokio::test]
+async fn undelivered_messages_list_sends_filters_and_range() {
+    use crate::rest_api::undelivered_messages::{list, Params};
+    use crate::transport::HttpResponse;
+
+    let recording = Recording::default().respond(
+        HttpResponse::new(206)
+            .header("Content-Range", "items 0-1/1")
+            .body(
+                serde_json::json!([{
+                    "id": "1",
+                    "messageType": "EMAIL",
+                    "recipientAddress": "bounce@example.com",
+                    "status": "BOUNCED",
+                }])
+                .to_string(),
+            ),
+    );
+    let params = Params::new()
+        .date_range(ty::DateRange::new(
+            ty::Date::new(2024, 1, 1, 0, 0, 0),
+            ty::Date::new(2024, 2, 1, 0, 0, 0),
+        ))
+        .received_from(ty::Date::new(2024, 1, 15, 0, 0, 0))
+        .received_to(ty::Date::new(2024, 1, 16, 0, 0, 0))
+        .add_events(true)
+        .sort_order(ty::SortOrder::Desc);
+    let page = list(&recording.client(), &params, &(0..20)).await.unwrap();
+    assert_eq!(page.items[0].recipient_address, "bounce@example.com");
+    #[allow(deprecated)]
+    let recipient = page.items[0].recipient_adr();
+    assert_eq!(recipient, "bounce@example.com");
+
+    let requests = recording.requests();
+    assert_eq!(
+        requests[0].url,
+        "https://api.flowmailer.net/1234/undeliveredmessages\
+         ;daterange=2024-01-01T00:00:00Z,2024-02-01T00:00:00Z\
+         ?addevents=true&addheaders=false&addtags=false\
+         &receivedFrom=2024-01-15T00%3A00%3A00Z&receivedTo=2024-01-16T00%3A00%3A00Z\
+         &sortorder=DESC"
+    );
+    assert_eq!(requests[0].header("range"), Some("items=0-20"));
+}
+
+#[tokio::test]
+async fn recipient_messages_sends_encoded_address_and_filters() {
+    use crate::rest_api::recipient::{messages, MessagesParams};
+    use crate::transport::{HttpResponse, Method};
+
+    let recording = Recording::default().respond(
+        HttpResponse::new(206)
+            .header("Content-Range", "items 10-10/10")
+            .body("[]"),
+    );
+    let params = MessagesParams::new()
+        .date_range(ty::DateRange::new(
+            ty::Date::new(2024, 1, 1, 0, 0, 0),
+            ty::Date::new(2024, 2, 1, 0, 0, 0),
+        ))
+        .add_headers(true)
+        .add_tags(true);
+    let page = messages(
+        &recording.client(),
+        "customer+news@example.com",
+        &params,
+        &(10..20),
+    )
+    .await
+    .unwrap();
+    assert!(page.items.is_empty());
+    assert_eq!(page.next_range(), None);
+
+    let requests = recording.requests();
+    assert_eq!(requests[0].method, Method::Get);
+    assert_eq!(
+        requests[0].url,
+        "https://api.flowmailer.net/1234/recipient/customer%2Bnews@example.com/messages\
+         ;daterange=2024-01-01T00:00:00Z,2024-02-01T00:00:00Z\
+         ?addevents=false&addheaders=true&addonlinelink=false&addtags=true"
+    );
+    assert_eq!(requests[0].header("range"), Some("items=10-20"));
+}
+
+#[tokio::test]
+async fn stats_calls_use_their_endpoints() {
+    use crate::rest_api::{flows, sources, stats};
+    use crate::transport::HttpResponse;
+
+    let datasets = || {
+        HttpResponse::new(200).body(
+            r#"{"list":[{"name":"sent","samples":[{"timestamp":"2024-01-01T00:00:00Z","value":3}]}]}"#,
+        )
+    };
+    let recording = Recording::default()
+        .respond(datasets())
+        .respond(datasets())
+        .respond(datasets())
+        .respond(datasets());
+    let client = recording.client();
+    let params = stats::Params::new(ty::DateRange::new(
+        ty::Date::new(2024, 1, 1, 0, 0, 0),
+        ty::Date::new(2024, 1, 2, 0, 0, 0),
+    ));
+    let sets = stats::account(&client, &params).await.unwrap();
+    assert_eq!(sets.get(ty::Metric::Sent).unwrap().samples[0].value, 3);
+    stats::message_stats(&client, &params).await.unwrap();
+    flows::stats(&client, "7", &params).await.unwrap();
+    sources::stats(&client, "8", &params).await.unwrap();
+
+    let daterange = ";daterange=2024-01-01T00:00:00Z,2024-01-02T00:00:00Z";
+    let urls: Vec<_> = recording.requests().into_iter().map(|r| r.url).collect();
+    assert_eq!(
+        urls,
+        [
+            format!("https://api.flowmailer.net/1234/stats{daterange}"),
+            format!("https://api.flowmailer.net/1234/message_stats{daterange}"),
+            format!("https://api.flowmailer.net/1234/flows/7/stats{daterange}"),
+            format!("https://api.flowmailer.net/1234/sources/8/stats{daterange}"),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn stats_params_send_interval_and_metrics() {
+    use crate::rest_api::stats;
+    use crate::transport::HttpResponse;
+
+    let recording = Recording::default().respond(HttpResponse::new(200).body(r#"{"list":[]}"#));
+    let params = stats::Params::new(ty::DateRange::new(
+        ty::Date::new(2024, 1, 1, 0, 0, 0),
+        ty::Date::new(2024, 1, 2, 0, 0, 0),
+    ))
+    .interval(3600)
+    .metrics([ty::Metric::Delivered, ty::Metric::Clicked]);
+    let sets = stats::account(&recording.client(), &params).await.unwrap();
+    assert!(sets.get(ty::Metric::Delivered).is_none());
+
+    assert_eq!(
+        recording.requests()[0].url,
+        "https://api.flowmailer.net/1234/stats\
+         ;daterange=2024-01-01T00:00:00Z,2024-01-02T00:00:00Z;interval=3600\
+         ?metrics=delivered%2Cclicked"
+    );
+}
+
+#[tokio::test]
+async fn sender_domains_requests_match_the_api() {
+    use crate::resources::SenderDomain;
+    use crate::rest_api::sender_domains;
+    use crate::transport::{HttpResponse, Method};
+
+    let stored = serde_json::json!({
+        "id": "d1",
+        "senderDomain": "example.com",
+        "returnPathDomain": "bounce.example.com",
+        "webDomain": "links.example.com",
+        "dnsRecords": [{ "name": "example.com", "type": "TXT", "value": "v=spf1 ..." }],
+    })
+    .to_string();
+    let recording = Recording::default()
+        .respond(HttpResponse::new(200).body("[]"))
+        .respond(HttpResponse::new(201).header(
+            "Location",
+            "https://api.flowmailer.net/1234/sender_domains/d1",
+        ))
+        .respond(HttpResponse::new(200).body(stored.clone()))
+        .respond(HttpResponse::new(200).body(stored.clone()))
+        .respond(HttpResponse::new(200).body(stored))
+        .respond(HttpResponse::new(200));
+    let client = recording.client();
+    let domain = SenderDomain::new("example.com", "bounce.example.com", "links.example.com");
+
+    assert!(sender_domains::list(&client).await.unwrap().is_empty());
+    let created = sender_domains::create(&client, domain.clone())
+        .await
+        .unwrap();
+    assert_eq!(created.id.as_deref(), Some("d1"));
+    assert_eq!(created.dns_records.len(), 1);
+    sender_domains::by_domain(&client, "example.com", true)
+        .await
+        .unwrap();
+    sender_domains::validate(&client, &domain).await.unwrap();
+    sender_domains::delete(&client, "d1").await.unwrap();
+
+    let requests = recording.requests();
+    let base = "https://api.flowmailer.net/1234/sender_domains";
+    let calls: Vec<_> = requests
+        .iter()
+        .map(|request| (request.method, request.url.strip_prefix(base).unwrap()))
+        .collect();
+    assert_eq!(
+        calls,
+        [
+            (Method::Get, ""),
+            (Method::Post, ""),
+            (Method::Get, "/d1?validate=false"),
+            (Method::Get, "/by_domain/example.com?validate=true"),
+            (Method::Post, "/validate"),
+            (Method::Delete, "/d1"),
+        ]
+    );
+    let body = serde_json::json!({
+        "returnPathDomain": "bounce.example.com",
+        "senderDomain": "example.com",
+        "webDomain": "links.example.com",
+    });
+    for request in [&requests[1], &requests[4]] {
+        let sent: serde_json::Value =
+            serde_json::from_slice(request.body.as_deref().unwrap()).unwrap();
+        assert_eq!(sent, body);
+    }
+}
+
+#[tokio::test]
+async fn sender_identities_requests_match_the_api() {
+    use crate::resources::SenderIdentity;
+    use crate::rest_api::sender_identities;
+    use crate::transport::{HttpResponse, Method};
+    use ty::sender_identity::SelectionType;
+
+    let identity = SenderIdentity::new(
+        "brand",
+        SelectionType::Domain,
+        "brand.com",
+        "hello@brand.com",
+        "Brand",
+    );
+    let stored = merged::<SenderIdentity>(
+        serde_json::to_value(&identity).unwrap(),
+        serde_json::json!({ "id": "s1" }),
+    );
+    let stored = serde_json::to_string(&stored).unwrap();
+    let recording = Recording::default()
+        .respond(HttpResponse::new(200).body(format!("[{stored}]")))
+        .respond(HttpResponse::new(201).header(
+            "Location",
+            "https://api.flowmailer.net/1234/sender_identities/s1",
+        ))
+        .respond(HttpResponse::new(200).body(stored.clone()))
+        .respond(HttpResponse::new(200).body(stored))
+        .respond(HttpResponse::new(200));
+    let client = recording.client();
+
+    let listed = sender_identities::list(&client).await.unwrap();
+    assert_eq!(listed[0].id.as_deref(), Some("s1"));
+    let created = sender_identities::create(&client, identity.clone())
+        .await
+        .unwrap();
+    assert_eq!(created.id.as_deref(), Some("s1"));
+    let mut renamed = identity;
+    renamed.sender_name = "Brand Support".to_string();
+    sender_identities::update(&client, "s1", renamed)
+        .await
+        .unwrap();
+    sender_identities::delete(&client, "s1").await.unwrap();
+
+    let requests = recording.requests();
+    let base = "https://api.flowmailer.net/1234/sender_identities";
+    let calls: Vec<_> = requests
+        .iter()
+        .map(|request| (request.method, request.url.strip_prefix(base).unwrap()))
+        .collect();
+    assert_eq!(
+        calls,
+        [
+            (Method::Get, ""),
+            (Method::Post, ""),
+            (Method::Get, "/s1"),
+            (Method::Put, "/s1"),
+            (Method::Delete, "/s1"),
+        ]
+    );
+    let body = |request: &crate::transport::HttpRequest| -> serde_json::Value {
+        serde_json::from_slice(request.body.as_deref().unwrap()).unwrap()
+    };
+    let created = body(&requests[1]);
+    assert_eq!(created["selectionType"], "domain");
+    assert_eq!(created["selectionValue"], "brand.com");
+    assert_eq!(created["senderEmail"], "hello@brand.com");
+    assert!(created.get("id").is_none());
+    assert_eq!(body(&requests[3])["senderName"], "Brand Support");
+}
+
+#[tokio::test]
+async fn source_credentials_requests_match_the_api() {
+    use crate::resources::Credentials;
+    use crate::rest_api::source_credentials;
+    use crate::transport::{HttpResponse, Method};
+
+    let stored = serde_json::json!({
+        "id": "u1",
+        "description": "legacy smtp",
+        "protocol": "SMTP",
+        "sourceId": "5",
+        "username": "legacy",
+    })
+    .to_string();
+    let recording = Recording::default()
+        .respond(HttpResponse::new(200).body(format!("[{stored}]")))
+        .respond(HttpResponse::new(201).header(
+            "Location",
+            "https://api.flowmailer.net/1234/sources/5/users/u1",
+        ))
+        .respond(HttpResponse::new(200).body(stored.clone()))
+        .respond(HttpResponse::new(200).body(stored))
+        .respond(HttpResponse::new(200));
+    let client = recording.client();
+    let credentials = Credentials::new(
+        "legacy smtp",
+        ty::credentials::Protocol::Smtp,
+        "legacy",
+        "hunter2",
+    );
+
+    let listed = source_credentials::list(&client, "5").await.unwrap();
+    assert_eq!(listed[0].id.as_deref(), Some("u1"));
+    let created = source_credentials::create(&client, "5", credentials.clone())
+        .await
+        .unwrap();
+    assert!(created.password.is_none());
+    let mut rotated = credentials;
+    rotated.password = Some(crate::auth::Secret::new("correct horse".to_string()));
+    source_credentials::update(&client, "5", "u1", rotated)
+        .await
+        .unwrap();
+    source_credentials::delete(&client, "5", "u1")
+        .await
+        .unwrap();
+
+    let requests = recording.requests();
+    let base = "https://api.flowmailer.net/1234/sources/5/users";
+    let calls: Vec<_> = requests
+        .iter()
+        .map(|request| (request.method, request.url.strip_prefix(base).unwrap()))
+        .collect();
+    assert_eq!(
+        calls,
+        [
+            (Method::Get, ""),
+            (Method::Post, ""),
+            (Method::Get, "/u1"),
+            (Method::Put, "/u1"),
+            (Method::Delete, "/u1"),
+        ]
+    );
+    let body = |request: &crate::transport::HttpRequest| -> serde_json::Value {
+        serde_json::from_slice(request.body.as_deref().unwrap()).unwrap()
+    };
+    let created = body(&requests[1]);
+    assert_eq!(created["protocol"], "SMTP");
+    assert_eq!(created["username"], "legacy");
+    assert_eq!(created["password"], "hunter2");
+    assert_eq!(body(&requests[3])["password"], "correct horse");
+}
+
+#[tokio::test]
+async fn client_credentials_reject_failed_token_responses() {
+    use crate::auth::{ClientCredentials, ClientId, ClientSecret, TokenProvider};
+    use crate::error::Kind;
+    use crate::transport::HttpResponse;
+
+    let recording =
+        Recording::default().respond(HttpResponse::new(401).body(r#"{"error":"invalid_client"}"#));
+    let credentials = ClientCredentials::new(
+        ClientId::new("id".to_string()),
+        ClientSecret::new("wrong".to_string()),
+    )
+    .token_url("https://login.example.com/token")
+    .transport(recording.clone());
+
+    let err = credentials.token().await.unwrap_err();
+    match err.kind {
+        Kind::Auth(msg) => {
+            assert!(msg.contains("401"), "{msg}");
+            assert!(msg.contains("invalid_client"), "{msg}");
+        }
+        kind => panic!("unexpected error kind {kind:?}"),
+    }
+    let requests = recording.requests();
+    assert_eq!(requests[0].url, "https://login.example.com/token");
+    assert_eq!(
+        requests[0].body.as_deref().unwrap(),
+        b"client_id=id&client_secret=wrong&grant_type=client_credentials&scope=api"
+    );
+}
+
+#[test]
+fn account_handles_share_client_state() {
+    use crate::auth::{AccountId, StaticToken};
+    use crate::Client;
+
+    let client = Client::with_token_provider(AccountId::from("1234"), StaticToken::new("token"));
+    let tenant = client.account("5678");
+    assert_eq!(client.account_id().as_str(), "1234");
+    assert_eq!(tenant.account_id().as_str(), "5678");
+    assert!(tenant.shares_state_with(&client));
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+fn blocking_client_runs_async_calls() {
+    use crate::auth::{AccountId, StaticToken};
+    use crate::blocking;
+
+    let client =
+        blocking::Client::with_token_provider(AccountId::from("1234"), StaticToken::new("token"))
+            .unwrap();
+    let tenant = client.account("5678");
+    let token = tenant.run(|client| client.authenticate(None)).unwrap();
+    assert_eq!(token.expose_secret(), "token");
+    assert_eq!(tenant.account_id().as_str(), "5678");
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+fn blocking_rest_api_wraps_async_calls() {
+    use crate::blocking::{self, rest_api::filters, rest_api::templates};
+    use crate::transport::{HttpResponse, Method};
+
+    let filter =
+        serde_json::json!([{ "id": "1", "address": "a@example.com", "messageType": "EMAIL" }]);
+    let transport = Recording::default()
+        .respond(HttpResponse::new(200).body("[]"))
+        .respond(HttpResponse::new(200))
+        .respond(HttpResponse::new(200).body(filter.to_string()));
+    let client = blocking::Client::from_async(transport.client()).unwrap();
+    assert!(templates::list(&client).unwrap().is_empty());
+    templates::delete(&client.account("5678"), "9").unwrap();
+    let exported: Vec<_> = filters::export(&client, Default::default(), 2)
+        .collect::<crate::Result<_>>()
+        .unwrap();
+    assert_eq!(exported.len(), 1);
+
+    let requests = transport.requests();
+    assert_eq!(requests[0].url, "https://api.flowmailer.net/1234/templates");
+    assert_eq!(requests[1].method, Method::Delete);
+    assert_eq!(
+        requests[1].url,
+        "https://api.flowmailer.net/5678/templates/9"
+    );
+    assert_eq!(requests[2].header("range"), Some("items=0-2"));
+}
+
+#[tokio::test]
+async fn requests_go_through_transport() {
+    use crate::transport::HttpResponse;
+    use crate::{request, resources};
+
+    let transport = Recording::default().respond(
+        HttpResponse::new(201).header("Location", "https://api.flowmailer.net/1234/flows/77"),
+    );
+    let client = transport.client();
+    let flow: resources::Flow = serde_json::from_value(serde_json::json!({
+        "description": "invoice", "steps": [], "templateId": "1"
+    }))
+    .unwrap();
+    let response = request::RequestBuilder::post(&client, "flows", &[])
+        .unwrap()
+        .query(&[("statistics", false)])
+        .body(serde_json::to_string(&flow).unwrap())
+        .execute()
+        .await
+        .unwrap();
+    assert_eq!(response.location_id().unwrap(), "77");
+
+    let requests = transport.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(
+        requests[0].url,
+        "https://api.flowmailer.net/1234/flows?statistics=false"
+    );
+    assert_eq!(requests[0].header("authorization"), Some("Bearer abc"));
+}
+
+#[tokio::test]
+async fn mail_with_source_id_submits_with_source_token() {
+    use crate::auth::StaticToken;
+    use crate::transport::HttpResponse;
+    use crate::{MailAddress, MailBuilder};
+
+    let created = |id: &str| {
+        HttpResponse::new(201).header(
+            "Location",
+            format!("https://api.flowmailer.net/1234/messages/{id}"),
+        )
+    };
+    let recording = Recording::default()
+        .respond(created("42"))
+        .respond(created("43"));
+    let client = recording
+        .client()
+        .with_source_token_provider("5", StaticToken::new("source-token"));
+    let mail = || {
+        MailBuilder::new_text(
+            MailAddress::new("noreply@example.com"),
+            MailAddress::new("customer@example.com"),
+            "Hello!",
+        )
+    };
+
+    let id = mail().set_source_id("5").send(&client).await.unwrap();
+    assert_eq!(id, "42");
+    assert_eq!(mail().send(&client).await.unwrap(), "43");
+
+    let requests = recording.requests();
+    assert_eq!(requests.len(), 2);
+    // flowmailer attributes the message to the source whose token submitted it
+    assert_eq!(
+        requests[0].url,
+        "https://api.flowmailer.net/1234/messages/submit"
+    );
+    assert_eq!(
+        requests[0].header("authorization"),
+        Some("Bearer source-token")
+    );
+    let body: serde_json::Value =
+        serde_json::from_slice(requests[0].body.as_deref().unwrap()).unwrap();
+    assert_eq!(body["recipientAddress"], "customer@example.com");
+    assert_eq!(body["text"], "Hello!");
+    assert_eq!(requests[1].header("authorization"), Some("Bearer abc"));
+
+    let err = mail().set_source_id("6").send(&client).await.unwrap_err();
+    assert!(matches!(err.kind, crate::error::Kind::Auth(_)));
+    assert_eq!(recording.requests().len(), 2);
+}
+
+#[cfg(feature = "testing")]
+#[tokio::test]
+async fn mock_server_captures_submitted_mail() {
+    use crate::resources::SimulateMessage;
+    use crate::rest_api::{message, message_events};
+    use crate::testing::MockServer;
+    use crate::{MailAddress, MailBuilder};
+
+    let server = MockServer::start().await.unwrap();
+    let client = server.client("1234");
+    MailBuilder::new_template(
+        MailAddress::new("sender@example.com"),
+        MailAddress::new("recipient@example.com"),
+        "invoice",
+    )
+    .set_subject("Your invoice")
+    .send(&client)
+    .await
+    .unwrap();
+
+    let submitted = server.submitted();
+    assert_eq!(submitted.len(), 1);
+    assert_eq!(submitted[0].flow_selector.as_deref(), Some("invoice"));
+
+    let id = &server.messages()[0].id;
+    let found = message::get(&client, id, true).await.unwrap();
+    assert_eq!(found.recipient_address, "recipient@example.com");
+    assert_eq!(found.events[0].ty, ty::message_event::Type::Delivered);
+    let found = message::get(&client, id, false).await.unwrap();
+    assert!(found.events.is_empty());
+
+    let events = message_events::list(&client, &message_events::Params::new(), &(0..10))
+        .await
+        .unwrap();
+    assert_eq!(events.items.len(), 3);
+    let page = message_events::list(&client, &message_events::Params::new(), &(1..2))
+        .await
+        .unwrap();
+    assert_eq!(page.items.len(), 1);
+    assert_eq!(page.items[0].ty, ty::message_event::Type::Processed);
+    assert_eq!(page.next_range(), Some(2..3));
+    assert!(message::get(&server.client("5678"), id, false)
+        .await
+        .is_err());
+
+    let simulated = message::simulate(&client, SimulateMessage::from(submitted[0].clone()))
+        .await
+        .unwrap();
+    assert_eq!(simulated.subject.as_deref(), Some("Your invoice"));
+}
+
+#[tokio::test]
+async fn capture_mode_stores_mail_in_outbox() {
+    use crate::auth::{AccountId, StaticToken};
+    use crate::outbox::{DeliveryMode, Outbox};
+    use crate::{Client, MailAddress, MailBuilder};
+
+    let outbox = Outbox::new();
+    let client = Client::with_token_provider(AccountId::from("1234"), StaticToken::new("token"))
+        .delivery_mode(DeliveryMode::Capture(outbox.clone()));
+
+    let waiting = tokio::spawn({
+        let outbox = outbox.clone();
+        async move { outbox.wait_for(2).await }
+    });
+    let mail = |recipient: &str| {
+        MailBuilder::new_text(
+            MailAddress::new("sender@example.com"),
+            MailAddress::new(recipient),
+            "Hello!",
+        )
+    };
+    let first = mail("first@example.com").send(&client).await.unwrap();
+    mail("second@example.com")
+        .send(&client.account("5678"))
+        .await
+        .unwrap();
+
+    let captured = waiting.await.unwrap();
+    assert_eq!(captured.len(), 2);
+    assert_eq!(captured[1].account_id, "5678");
+    assert_eq!(
+        outbox.get(&first).unwrap().message.recipient_address,
+        "first@example.com"
+    );
+    outbox.clear().unwrap();
+    assert!(outbox.is_empty());
+}
+
+#[tokio::test]
+async fn delivery_mode_applies_to_existing_handles() {
+    use crate::auth::{AccountId, StaticToken};
+    use crate::outbox::{DeliveryMode, Outbox};
+    use crate::{Client, MailAddress, MailBuilder};
+
+    let client = Client::with_token_provider(AccountId::from("1234"), StaticToken::new("token"));
+    let tenant = client.account("5678");
+    let outbox = Outbox::new();
+    let client = client.delivery_mode(DeliveryMode::Capture(outbox.clone()));
+
+    assert!(matches!(tenant.delivery(), DeliveryMode::Capture(_)));
+    MailBuilder::new_text(
+        MailAddress::new("sender@example.com"),
+        MailAddress::new("recipient@example.com"),
+        "Hello!",
+    )
+    .send(&tenant)
+    .await
+    .unwrap();
+    assert_eq!(outbox.list()[0].account_id, "5678");
+    assert!(tenant.shares_state_with(&client));
+}
+
+#[tokio::test]
+async fn on_disk_outbox_continues_existing_ids() {
+    use crate::auth::{AccountId, StaticToken};
+    use crate::outbox::{DeliveryMode, Outbox};
+    use crate::{Client, MailAddress, MailBuilder};
+
+    let dir = std::env::temp_dir().join(format!("flowmailer-outbox-{}", std::process::id()));
+    async fn send(outbox: &Outbox) -> crate::Result<String> {
+        let client =
+            Client::with_token_provider(AccountId::from("1234"), StaticToken::new("token"))
+                .delivery_mode(DeliveryMode::Capture(outbox.clone()));
+        MailBuilder::new_text(
+            MailAddress::new("sender@example.com"),
+            MailAddress::new("recipient@example.com"),
+            "Hello!",
+        )
+        .send(&client)
+        .await
+    }
+
+    let first = send(&Outbox::on_disk(&dir).unwrap()).await.unwrap();
+    // a new process starts with an empty outbox on the same directory
+    let restarted = Outbox::on_disk(&dir).unwrap();
+    let second = send(&restarted).await.unwrap();
+
+    assert_ne!(first, second);
+    assert!(dir.join(format!("{first}.json")).exists());
+    let written: crate::outbox::CapturedMessage =
+        serde_json::from_slice(&std::fs::read(dir.join(format!("{second}.json"))).unwrap())
+            .unwrap();
+    assert_eq!(written.message.recipient_address, "recipient@example.com");
+    assert_eq!(restarted.len(), 1);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn event_flow_rules_round_trip_unknown_event_types() {
+    use crate::resources::EventFlowRuleSimple;
+    use crate::rest_api::event_flow_rules;
+    use crate::transport::HttpResponse;
+
+    let rules = serde_json::json!([
+        { "eventFlowId": "1", "eventType": "CLICK", "linkName": "unsubscribe" },
+        { "eventFlowId": "2", "eventType": "DEFERRED" },
+    ]);
+    let transport = Recording::default()
+        .respond(HttpResponse::new(200).body(rules.to_string()))
+        .respond(HttpResponse::new(200));
+    let client = transport.client();
+
+    let mut fetched = event_flow_rules::get(&client).await.unwrap();
+    assert_eq!(fetched[0].event_type, ty::message_event::Type::Click);
+    assert_eq!(
+        fetched[1].event_type,
+        ty::message_event::Type::Other("DEFERRED".to_string())
+    );
+    fetched.push(EventFlowRuleSimple::new(
+        ty::message_event::Type::Bounce,
+        "3",
+    ));
+    event_flow_rules::replace(&client, &fetched).await.unwrap();
+
+    let requests = transport.requests();
+    assert_eq!(
+        requests[1].url,
+        "https://api.flowmailer.net/1234/event_flow_rules"
+    );
+    let sent: serde_json::Value =
+        serde_json::from_slice(requests[1].body.as_deref().unwrap()).unwrap();
+    let types: Vec<_> = sent
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|rule| rule["eventType"].as_str().unwrap())
+        .collect();
+    assert_eq!(types, ["CLICK", "DEFERRED", "BOUNCE"]);
+    assert_eq!(sent[0]["linkName"], "unsubscribe");
+}
+
+#[tokio::test]
+async fn event_flows_create_fetches_stored_flow() {
+    use crate::resources::{EventFlow, FlowStep};
+    use crate::rest_api::event_flows;
+    use crate::transport::{HttpResponse, Method};
+
+    let stored = serde_json::json!({
+        "id": "42",
+        "description": "bounces",
+        "steps": [{ "id": "s1", "type": "qamail", "to": "qa@example.com" }],
+    });
+    let transport = Recording::default()
+        .respond(
+            HttpResponse::new(201)
+                .header("Location", "https://api.flowmailer.net/1234/event_flows/42"),
+        )
+        .respond(HttpResponse::new(200).body(stored.to_string()));
+    let client = transport.client();
+
+    let flow = EventFlow {
+        description: "bounces".to_string(),
+        id: None,
+        parent_id: None,
+        steps: Box::new([FlowStep::qamail("qa@example.com")]),
+    };
+    let created = event_flows::create(&client, flow).await.unwrap();
+    assert_eq!(created.id.as_deref(), Some("42"));
+
+    let requests = transport.requests();
+    assert_eq!(requests[0].method, Method::Post);
+    assert_eq!(
+        requests[0].url,
+        "https://api.flowmailer.net/1234/event_flows"
+    );
+    assert_eq!(requests[1].method, Method::Get);
+    assert_eq!(
+        requests[1].url,
+        "https://api.flowmailer.net/1234/event_flows/42"
+    );
+}
+
+#[tokio::test]
+async fn flows_create_fetches_flow_from_location() {
+    use crate::resources::Flow;
+    use crate::rest_api::flows;
+    use crate::transport::{HttpResponse, Method};
+
+    let stored = serde_json::json!({
+        "id": "77",
+        "description": "invoice",
+        "templateId": "1",
+        "steps": [{ "id": "s1", "type": "subject", "subjectTemplate": "Your invoice" }],
+    });
+    let transport = Recording::default()
+        .respond(
+            HttpResponse::new(201).header("Location", "https://api.flowmailer.net/1234/flows/77"),
+        )
+        .respond(HttpResponse::new(200).body(stored.to_string()))
+        .respond(HttpResponse::new(200).body(format!("[{stored}]")))
+        .respond(HttpResponse::new(200));
+    let client = transport.client();
+
+    let flow: Flow = merged(stored.clone(), serde_json::json!({ "id": null }));
+    let created = flows::create(&client, flow).await.unwrap();
+    assert_eq!(created.id.as_deref(), Some("77"));
+    let listed = flows::list(&client, true).await.unwrap();
+    assert_eq!(listed.len(), 1);
+    flows::delete(&client, "77").await.unwrap();
+
+    let requests = transport.requests();
+    let calls: Vec<_> = requests
+        .iter()
+        .map(|request| (request.method, request.url.as_str()))
+        .collect();
+    assert_eq!(
+        calls,
+        [
+            (Method::Post, "https://api.flowmailer.net/1234/flows"),
+            (
+                Method::Get,
+                "https://api.flowmailer.net/1234/flows/77?statistics=false"
+            ),
+            (
+                Method::Get,
+                "https://api.flowmailer.net/1234/flows?statistics=true"
+            ),
+            (Method::Delete, "https://api.flowmailer.net/1234/flows/77"),
+        ]
+    );
+    let sent: serde_json::Value =
+        serde_json::from_slice(requests[0].body.as_deref().unwrap()).unwrap();
+    assert!(sent.get("id").is_none());
+    assert_eq!(sent["steps"][0]["type"], "subject");
+}
+
+#[tokio::test]
+async fn filter_export_pages_by_content_range() {
+    use crate::rest_api::filters;
+    use crate::transport::HttpResponse;
+
+    let filter = |address: &str| serde_json::json!({ "id": address, "address": address, "messageType": "EMAIL" });
+    let page = |items: &[&str]| {
+        let items: Vec<_> = items.iter().map(|address| filter(address)).collect();
+        serde_json::Value::Array(items).to_string()
+    };
+    let transport = Recording::default()
+        .respond(
+            HttpResponse::new(200)
+                .header("Content-Range", "items 0-2/3")
+                .body(page(&["a@example.com", "b@example.com"])),
+        )
+        .respond(HttpResponse::new(500).body("unavailable"))
+        .respond(
+            HttpResponse::new(200)
+                .header("Content-Range", "items 2-3/3")
+                .body(page(&["c@example.com"])),
+        );
+    let client = transport.client();
+
+    let mut export = filters::export(&client, filters::Params::new(), 2);
+    let mut addresses = Vec::new();
+    let mut errors = 0;
+    while let Some(filter) = export.next().await {
+        match filter {
+            Ok(filter) => addresses.push(filter.address),
+            Err(_) => errors += 1,
+        }
+    }
+    assert_eq!(errors, 1);
+    assert_eq!(
+        addresses,
+        ["a@example.com", "b@example.com", "c@example.com"]
+    );
+    let ranges: Vec<_> = transport
+        .requests()
+        .iter()
+        .map(|request| request.header("range").unwrap().to_string())
+        .collect();
+    assert_eq!(ranges, ["items=0-2", "items=2-4", "items=2-4"]);
+}
+
+#[tokio::test]
+async fn filter_export_pages_without_content_range() {
+    use crate::rest_api::filters;
+    use crate::transport::HttpResponse;
+
+    let page = |addresses: &[&str]| {
+        let items: Vec<_> = addresses
+            .iter()
+            .map(|address| serde_json::json!({ "id": address, "address": address, "messageType": "SMS" }))
+            .collect();
+        HttpResponse::new(200).body(serde_json::Value::Array(items).to_string())
+    };
+    let transport = Recording::default()
+        .respond(page(&["1", "2"]))
+        .respond(page(&["3"]));
+    let client = transport.client();
+
+    let mut export = filters::export(&client, filters::Params::new(), 2);
+    let mut addresses = Vec::new();
+    while let Some(filter) = export.next().await {
+        addresses.push(filter.unwrap().address);
+    }
+    // a short page ends the export
+    assert_eq!(addresses, ["1", "2", "3"]);
+    assert_eq!(transport.requests().len(), 2);
+}
@@ -0,0 +1,376 @@
+//! In-process mock of the flowmailer API for integration tests.
+//!
+//! [`MockServer`] listens on a local port and implements the OAuth token endpoint,
+//! `messages/submit`, message lookup, `message_events` and `messages/simulate`, backed by
+//! in-memory state. Point a [`Client`] at it with [`MockServer::client`] and assert on the
+//! captured [`SubmitMessage`] payloads with [`MockServer::submitted`].
+//!
+//! # Example
+//! ```no_run
+//! use flowmailer::testing::MockServer;
+//! use flowmailer::{MailAddress, MailBuilder};
+//!
+//! # async fn example() -> flowmailer::Result<()> {
+//! let server = MockServer::start().await?;
+//! MailBuilder::new_text(
+//!     MailAddress::new("sender@example.com"),
+//!     MailAddress::new("recipient@example.com"),
+//!     "Hello!",
+//! )
+//! .send(&server.client("1234"))
+//! .await?;
+//! assert_eq!(server.submitted()[0].recipient_address, "recipient@example.com");
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::auth::{AccountId, CachingTokenProvider, ClientCredentials, ClientId, ClientSecret};
+use crate::error::{self, Error, Result};
+use crate::resources::{SimulateMessage, SubmitMessage};
+use crate::{ty, Client};
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex, MutexGuard};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+/// Access token issued by the mock token endpoint.
+pub const ACCESS_TOKEN: &str = "mock-access-token";
+
+/// A message submitted to the mock server.
+#[derive(Clone)]
+pub struct SubmittedMessage {
+    /// Message ID, as returned in the `Location` header.
+    pub id: String,
+    pub account_id: String,
+    pub submitted: ty::Date,
+    pub message: SubmitMessage,
+}
+
+#[derive(Default)]
+struct State {
+    messages: Vec<SubmittedMessage>,
+    next_id: u64,
+}
+
+/// Local HTTP server mocking the flowmailer API. Stops when dropped.
+pub struct MockServer {
+    url: String,
+    state: Arc<Mutex<State>>,
+    task: JoinHandle<()>,
+}
+
+impl MockServer {
+    /// Starts a server on a free local port. Must be called within a tokio runtime.
+    pub async fn start() -> Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await.map_err(io_error)?;
+        let url = format!("http://{}", listener.local_addr().map_err(io_error)?);
+        let state = Arc::new(Mutex::new(State::default()));
+        let task = tokio::spawn(serve(listener, url.clone(), Arc::clone(&state)));
+        Ok(Self { url, state, task })
+    }
+
+    /// Returns the base URL of the mock API.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Returns the URL of the mock OAuth token endpoint.
+    pub fn token_url(&self) -> String {
+        format!("{}/oauth/token", self.url)
+    }
+
+    /// Returns a client for `account_id` that authenticates against and sends to this server.
+    pub fn client(&self, account_id: impl Into<AccountId>) -> Client {
+        let credentials = ClientCredentials::new(
+            ClientId::new("mock-client".to_string()),
+            ClientSecret::new("mock-secret".to_string()),
+        )
+        .token_url(self.token_url());
+        Client::with_token_provider(account_id.into(), CachingTokenProvider::new(credentials))
+            .base_url(&self.url)
+    }
+
+    /// Returns the messages submitted so far, oldest first.
+    pub fn messages(&self) -> Vec<SubmittedMessage> {
+        lock(&self.state).messages.clone()
+    }
+
+    /// Returns the payloads of the messages submitted so far, oldest first.
+    pub fn submitted(&self) -> Vec<SubmitMessage> {
+        lock(&self.state)
+            .messages
+            .iter()
+            .map(|submitted| submitted.message.clone())
+            .collect()
+    }
+
+    /// Forgets all submitted messages.
+    pub fn reset(&self) {
+        lock(&self.state).messages.clear();
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+fn lock(state: &Mutex<State>) -> MutexGuard<'_, State> {
+    state.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+fn io_error(err: std::io::Error) -> Error {
+    Error::new(error::Kind::Other(format!("mock server: {err}")))
+}
+
+struct Request {
+    method: String,
+    path: String,
+    query: String,
+    authorization: Option<String>,
+    range: Option<String>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    /// Returns the decoded value of query parameter `key`, if present.
+    fn query_param(&self, key: &str) -> Option<String> {
+        serde_urlencoded::from_str::<Vec<(String, String)>>(&self.query)
+            .ok()?
+            .into_iter()
+            .find_map(|(name, value)| (name == key).then_some(value))
+    }
+}
+
+struct Reply {
+    status: u16,
+    headers: Vec<(&'static str, String)>,
+    body: Value,
+}
+
+impl Reply {
+    fn json(status: u16, body: Value) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body,
+        }
+    }
+
+    fn error(status: u16, message: impl Into<String>) -> Self {
+        Self::json(status, json!({ "message": message.into() }))
+    }
+
+    fn header(mut self, name: &'static str, value: String) -> Self {
+        self.headers.push((name, value));
+        self
+    }
+}
+
+async fn serve(listener: TcpListener, url: String, state: Arc<Mutex<State>>) {
+    while let Ok((stream, _)) = listener.accept().await {
+        let (url, state) = (url.clone(), Arc::clone(&state));
+        tokio::spawn(async move {
+            let _ = handle_connection(stream, &url, &state).await;
+        });
+    }
+}
+
+/// Handles a single request; the connection is closed after the response.
+async fn handle_connection(
+    stream: TcpStream,
+    url: &str,
+    state: &Mutex<State>,
+) -> std::io::Result<()> {
+    let mut stream = BufReader::new(stream);
+    let mut line = String::new();
+    stream.read_line(&mut line).await?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let target = parts.next().unwrap_or_default();
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let (path, query) = (path.to_string(), query.to_string());
+
+    let mut content_length = 0;
+    let mut authorization = None;
+    let mut range = None;
+    loop {
+        line.clear();
+        if stream.read_line(&mut line).await? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            let value = value.trim();
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.parse().unwrap_or(0);
+            } else if name.eq_ignore_ascii_case("authorization") {
+                authorization = Some(value.to_string());
+            } else if name.eq_ignore_ascii_case("range") {
+                range = Some(value.to_string());
+            }
+        }
+    }
+    let mut body = vec![0; content_length];
+    stream.read_exact(&mut body).await?;
+
+    let request = Request {
+        method,
+        path,
+        query,
+        authorization,
+        range,
+        body,
+    };
+    let reply = route(&request, url, state);
+    let body = reply.body.to_string();
+    let mut response = format!(
+        "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n",
+        reply.status,
+        body.len()
+    );
+    for (name, value) in reply.headers {
+        response.push_str(&format!("{name}: {value}\r\n"));
+    }
+    response.push_str("\r\n");
+    response.push_str(&body);
+    let mut stream = stream.into_inner();
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+fn route(request: &Request, url: &str, state: &Mutex<State>) -> Reply {
+    if request.method == "POST" && request.path == "/oauth/token" {
+        return Reply::json(
+            200,
+            json!({ "access_token": ACCESS_TOKEN, "token_type": "bearer", "expires_in": 3600 }),
+        );
+    }
+    if request.authorization.as_deref() != Some(&format!("Bearer {ACCESS_TOKEN}")) {
+        return Reply::error(401, "invalid access token");
+    }
+
+    // strip matrix parameters such as `;daterange=...`
+    let segments: Vec<&str> = request
+        .path
+        .trim_start_matches('/')
+        .split('/')
+        .map(|segment| segment.split(';').next().unwrap_or_default())
+        .collect();
+    match (request.method.as_str(), segments.as_slice()) {
+        ("POST", [account_id, "messages", "submit"]) => {
+            match serde_json::from_slice::<SubmitMessage>(&request.body) {
+                Ok(message) => {
+                    let mut state = lock(state);
+                    state.next_id += 1;
+                    let id = format!("mock{:016}", state.next_id);
+                    state.messages.push(SubmittedMessage {
+                        id: id.clone(),
+                        account_id: account_id.to_string(),
+                        submitted: ty::Date::now(),
+                        message,
+                    });
+                    Reply::json(201, Value::Null)
+                        .header("Location", format!("{url}/{account_id}/messages/{id}"))
+                }
+                Err(err) => Reply::error(400, err.to_string()),
+            }
+        }
+        ("POST", [_, "messages", "simulate"]) => {
+            match serde_json::from_slice::<SimulateMessage>(&request.body) {
+                Ok(message) => Reply::json(
+                    200,
+                    json!({
+                        "data": message.data,
+                        "flow": message.flow_selector.map(|selector| {
+                            json!({ "id": "mock", "description": selector })
+                        }),
+                        "html": message.html,
+                        "messageType": message.message_type,
+                        "subject": message.subject,
+                        "text": message.text,
+                    }),
+                ),
+                Err(err) => Reply::error(400, err.to_string()),
+            }
+        }
+        ("GET", [account_id, "messages", message_id]) => {
+            let state = lock(state);
+            match state.messages.iter().find(|submitted| {
+                submitted.account_id == *account_id && submitted.id == *message_id
+            }) {
+                Some(submitted) => {
+                    let mut message = message_json(submitted);
+                    if request.query_param("addevents").as_deref() == Some("true") {
+                        message["events"] = Value::Array(events_json(submitted));
+                    }
+                    Reply::json(200, message)
+                }
+                None => Reply::error(404, format!("message {message_id} not found")),
+            }
+        }
+        ("GET", [account_id, "message_events"]) => {
+            let state = lock(state);
+            let events: Vec<Value> = state
+                .messages
+                .iter()
+                .filter(|submitted| submitted.account_id == *account_id)
+                .flat_map(events_json)
+                .collect();
+            let total = events.len();
+            let (start, end) = match request.range.as_deref().map(parse_range) {
+                Some(Some(range)) => range,
+                Some(None) => return Reply::error(400, "invalid Range header"),
+                None => (0, total),
+            };
+            let end = end.min(total);
+            let start = start.min(end);
+            Reply::json(206, Value::Array(events[start..end].to_vec()))
+                .header("Content-Range", format!("items {start}-{end}/{total}"))
+        }
+        _ => Reply::error(
+            404,
+            format!("{} {} is not mocked", request.method, request.path),
+        ),
+    }
+}
+
+/// Parses a `Range: items=<start>-<end>` header, with an exclusive end.
+fn parse_range(range: &str) -> Option<(usize, usize)> {
+    let (start, end) = range.trim().strip_prefix("items=")?.split_once('-')?;
+    Some((start.trim().parse().ok()?, end.trim().parse().ok()?))
+}
+
+fn message_json(submitted: &SubmittedMessage) -> Value {
+    let message = &submitted.message;
+    json!({
+        "id": submitted.id,
+        "messageType": message.message_type,
+        "recipientAddress": message.recipient_address,
+        "senderAddress": message.sender_address,
+        "from": message.header_from_address,
+        "subject": message.subject,
+        "status": "DELIVERED",
+        "submitted": submitted.submitted,
+        "backendStart": submitted.submitted,
+        "backendDone": submitted.submitted,
+        "tags": message.tags.as_deref().unwrap_or_default(),
+    })
+}
+
+fn events_json(submitted: &SubmittedMessage) -> Vec<Value> {
+    // newest first, like flowmailer
+    ["DELIVERED", "PROCESSED", "SUBMITTED"]
+        .iter()
+        .map(|ty| {
+            json!({
+                "id": format!("{}-{}", submitted.id, ty.to_lowercase()),
+                "messageId": submitted.id,
+                "received": submitted.submitted,
+                "inserted": submitted.submitted,
+                "type": ty,
+            })
+        })
+        .collect()
+}
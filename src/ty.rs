@@ -1,5 +1,9 @@
+use crate::error::{self, Error, Result};
 use base64::{engine::general_purpose::STANDARD, Engine};
 use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 pub type Int = u64;
 
@@ -36,39 +40,129 @@ impl Base64 {
     }
 }
 
+/// Range of list items, as used by the `range` request header (`items=0-10`).
+/// The end is exclusive.
 pub type ItemsRange = core::ops::Range<Int>;
 
 #[derive(Serialize, Deserialize)]
 pub struct RefRange {}
 
-#[derive(Serialize, Deserialize)]
-pub struct ContentRange {}
+/// Content range returned by paginated list calls (`items 0-10/123`).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContentRange {
+    /// Range of the returned items.
+    pub range: ItemsRange,
+    /// Total number of items, when known.
+    pub total: Option<Int>,
+}
 
-#[derive(Clone, Copy, Serialize, Deserialize)]
-pub struct Date {
-    year: u8,
-    month: u8,
-    day: u8,
-    hour: u8,
-    minute: u8,
-    second: u8,
+impl ContentRange {
+    /// Returns the range of the following page with the same page size.
+    /// Returns None when this was the last page.
+    pub fn next(&self) -> Option<ItemsRange> {
+        let size = self.range.end.checked_sub(self.range.start)?;
+        let next = self.range.end..self.range.end + size.max(1);
+        match self.total {
+            Some(total) if next.start >= total => None,
+            _ => Some(next),
+        }
+    }
+}
+
+impl FromStr for ContentRange {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let parse_err = || Error::new(error::Kind::Parse(format!("invalid content range '{s}'")));
+        let rest = s.trim().strip_prefix("items").ok_or_else(parse_err)?.trim();
+        let (range, total) = rest.split_once('/').ok_or_else(parse_err)?;
+        let (start, end) = range.split_once('-').ok_or_else(parse_err)?;
+        let total = match total.trim() {
+            "*" => None,
+            total => Some(total.parse().map_err(|_| parse_err())?),
+        };
+        Ok(Self {
+            range: start.trim().parse().map_err(|_| parse_err())?
+                ..end.trim().parse().map_err(|_| parse_err())?,
+            total,
+        })
+    }
 }
 
+/// ISO8601 date and time, e.g. `2023-01-31T12:00:00Z`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Date(String);
+
 impl Date {
-    pub fn test() -> Self {
-        Self {
-            year: 0,
-            month: 0,
-            day: 0,
-            hour: 0,
-            minute: 0,
-            second: 0,
-        }
+    /// Creates a new UTC Date from its components.
+    pub fn new(year: u16, month: u8, day: u8, hour: u8, minute: u8, second: u8) -> Self {
+        Self(format!(
+            "{year:04}-{month:02}-{day:02}T{hour:02}:{minute:02}:{second:02}Z"
+        ))
+    }
+
+    /// Returns the current UTC time.
+    pub fn now() -> Self {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+        let (days, time) = (seconds / 86_400, seconds % 86_400);
+        // civil date from days since 1970-01-01, see http://howardhinnant.github.io/date_algorithms.html
+        let z = days as i64 + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z - era * 146_097;
+        let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + i64::from(month <= 2);
+        Self::new(
+            year as u16,
+            month as u8,
+            day as u8,
+            (time / 3_600) as u8,
+            (time % 3_600 / 60) as u8,
+            (time % 60) as u8,
+        )
+    }
+
+    /// Creates a new Date from an already formatted ISO8601 string.
+    pub fn from_iso8601(date: impl Into<String>) -> Self {
+        Self(date.into())
+    }
+
+    /// Returns the ISO8601 formatted date.
+    pub fn as_str(&self) -> &str {
+        &self.0
     }
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct DateRange {}
+impl Display for Date {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Date range, formatted as `start,end` when used as a matrix parameter.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DateRange {
+    pub start: Date,
+    pub end: Date,
+}
+
+impl DateRange {
+    pub fn new(start: Date, end: Date) -> Self {
+        Self { start, end }
+    }
+}
+
+impl Display for DateRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{},{}", self.start, self.end)
+    }
+}
 
 #[derive(Clone, Copy, Serialize, Deserialize)]
 pub struct Empty;
@@ -81,7 +175,7 @@ pub enum TyCustom {
 }
 
 /// Valid values: `EMAIL`, `SMS` and `LETTER`.
-#[derive(Clone, Copy, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum MessageType {
     #[serde(rename = "EMAIL")]
     Email,
@@ -91,6 +185,16 @@ pub enum MessageType {
     Letter,
 }
 
+impl MessageType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Email => "EMAIL",
+            Self::SMS => "SMS",
+            Self::Letter => "LETTER",
+        }
+    }
+}
+
 /// Only applicable when `messageType` = `SMS`.
 /// Valid values: `NONE`, `FAILURE` and `DELIVERY_AND_FAILURE`.
 /// Defaults to `NONE` for SMS.
@@ -104,10 +208,206 @@ pub enum DeliveryNotificationType {
     DeliveryAndFailure,
 }
 
+/// Valid values: `hard` and `soft`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BounceType {
+    /// Permanent failure, the address should not be mailed again.
+    #[serde(rename = "hard")]
+    Hard,
+    /// Temporary failure, e.g. a full mailbox.
+    #[serde(rename = "soft")]
+    Soft,
+}
+
+/// Sort order for list calls.
+/// Valid values: `ASC` and `DESC`.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum SortOrder {
+    #[serde(rename = "ASC")]
+    Asc,
+    #[serde(rename = "DESC")]
+    Desc,
+}
+
+impl SortOrder {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Asc => "ASC",
+            Self::Desc => "DESC",
+        }
+    }
+}
+
+/// Statistics metric.
+/// Valid values: `processed`, `sent`, `delivered`, `opened` and `clicked`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Metric {
+    Processed,
+    Sent,
+    Delivered,
+    Opened,
+    Clicked,
+}
+
+impl Metric {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Processed => "processed",
+            Self::Sent => "sent",
+            Self::Delivered => "delivered",
+            Self::Opened => "opened",
+            Self::Clicked => "clicked",
+        }
+    }
+}
+
+pub mod message_event {
+    use super::*;
+
+    /// Message event type.
+    /// Event types not known to this crate are kept as `Other`, so they serialize unchanged.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub enum Type {
+        Aggregated,
+        Bounce,
+        Click,
+        Complaint,
+        Custom,
+        Delivered,
+        Error,
+        Hold,
+        Open,
+        Processed,
+        Sent,
+        Submitted,
+        Unsubscribe,
+        /// Event types not known to this crate.
+        Other(String),
+    }
+
+    impl Type {
+        pub fn as_str(&self) -> &str {
+            match self {
+                Self::Aggregated => "AGGREGATED",
+                Self::Bounce => "BOUNCE",
+                Self::Click => "CLICK",
+                Self::Complaint => "COMPLAINT",
+                Self::Custom => "CUSTOM",
+                Self::Delivered => "DELIVERED",
+                Self::Error => "ERROR",
+                Self::Hold => "HOLD",
+                Self::Open => "OPEN",
+                Self::Processed => "PROCESSED",
+                Self::Sent => "SENT",
+                Self::Submitted => "SUBMITTED",
+                Self::Unsubscribe => "UNSUBSCRIBE",
+                Self::Other(ty) => ty,
+            }
+        }
+    }
+
+    impl From<&str> for Type {
+        fn from(ty: &str) -> Self {
+            match ty {
+                "AGGREGATED" => Self::Aggregated,
+                "BOUNCE" => Self::Bounce,
+                "CLICK" => Self::Click,
+                "COMPLAINT" => Self::Complaint,
+                "CUSTOM" => Self::Custom,
+                "DELIVERED" => Self::Delivered,
+                "ERROR" => Self::Error,
+                "HOLD" => Self::Hold,
+                "OPEN" => Self::Open,
+                "PROCESSED" => Self::Processed,
+                "SENT" => Self::Sent,
+                "SUBMITTED" => Self::Submitted,
+                "UNSUBSCRIBE" => Self::Unsubscribe,
+                other => Self::Other(other.to_string()),
+            }
+        }
+    }
+
+    impl Serialize for Type {
+        fn serialize<S: serde::Serializer>(
+            &self,
+            serializer: S,
+        ) -> std::result::Result<S::Ok, S::Error> {
+            serializer.serialize_str(self.as_str())
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Type {
+        fn deserialize<D: serde::Deserializer<'de>>(
+            deserializer: D,
+        ) -> std::result::Result<Self, D::Error> {
+            Ok(Self::from(String::deserialize(deserializer)?.as_str()))
+        }
+    }
+}
+
+pub mod credentials {
+    use super::*;
+
+    /// Protocol source credentials are used with.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+    pub enum Protocol {
+        #[serde(rename = "SMTP")]
+        Smtp,
+        #[serde(rename = "SMTP_RCPT")]
+        SmtpRcpt,
+        #[serde(rename = "SMTP_HEADER")]
+        SmtpHeader,
+        #[serde(rename = "SMTP_IPONLY")]
+        SmtpIpOnly,
+        #[serde(rename = "SMPP")]
+        Smpp,
+    }
+}
+
+pub mod dns_record {
+    use super::*;
+
+    /// DNS record validation status.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+    pub enum Status {
+        /// DNS record is ok.
+        #[serde(rename = "OK")]
+        Ok,
+        /// DNS record is functional but could be improved.
+        #[serde(rename = "WARNING")]
+        Warning,
+        /// DNS record is not correct.
+        #[serde(rename = "ERROR")]
+        Error,
+        /// There was an error while validating this DNS record.
+        #[serde(rename = "UNKNOWN")]
+        Unknown,
+    }
+}
+
+pub mod sender_identity {
+    use super::*;
+
+    /// What a sender identity is selected by.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum SelectionType {
+        /// Every message of the account.
+        Account,
+        /// Messages processed by the flow with ID `selectionValue`.
+        Flow,
+        /// Messages from a sender address in domain `selectionValue`.
+        Domain,
+        /// Messages from sender address `selectionValue`.
+        Email,
+    }
+}
+
 pub mod flow_step {
     use super::*;
 
-    #[derive(Clone, Copy, Serialize, Deserialize)]
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
     pub enum Type {
         #[serde(rename = "analytics")]
         Analytics,
@@ -168,9 +468,9 @@ pub mod source {
     use super::*;
 
     /// Source type: `API`, `SMTP`, `SMTP_RCPT`, `SMTP_DOMAIN`, `SMPP` or `FLOWMAILER`.
-    #[derive(Clone, Copy, Serialize, Deserialize)]
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
     pub enum Type {
-        #[serde(rename = "APU")]
+        #[serde(rename = "API")]
         Api,
         #[serde(rename = "SMTP")]
         Smtp,
@@ -188,7 +488,7 @@ pub mod source {
 pub mod template {
     use super::*;
 
-    #[derive(Clone, Copy, Serialize, Deserialize)]
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
     pub enum Disposition {
         #[serde(rename = "attachment")]
         Attachment,
@@ -198,27 +498,64 @@ pub mod template {
         Related,
     }
 
-    /// Supported mime types:
-    /// - text/plain
-    /// - text/html
-    /// - application/vnd.flowmailer.itext+pdf
-    #[derive(Clone, Copy, Serialize, Deserialize)]
+    /// Mime type of a template.
+    /// Custom content types, e.g. `image/png`, are kept as `Custom`.
+    #[derive(Clone, Debug, PartialEq, Eq)]
     pub enum MimeType {
         /// text/plain
-        #[serde(rename = "text/plain")]
         TextPlain,
-        /// text/hmtl
-        #[serde(rename = "text/html")]
+        /// text/html
         TextHtml,
         /// application/vnd.flowmailer.itext+pdf
-        #[serde(rename = "application/vnd.flowmailer.itext+pdf")]
         Application,
+        /// Any other mime type.
+        Custom(String),
+    }
+
+    impl MimeType {
+        pub fn as_str(&self) -> &str {
+            match self {
+                Self::TextPlain => "text/plain",
+                Self::TextHtml => "text/html",
+                Self::Application => "application/vnd.flowmailer.itext+pdf",
+                Self::Custom(mime_type) => mime_type,
+            }
+        }
+    }
+
+    impl From<&str> for MimeType {
+        fn from(mime_type: &str) -> Self {
+            match mime_type {
+                "text/plain" => Self::TextPlain,
+                "text/html" => Self::TextHtml,
+                "application/vnd.flowmailer.itext+pdf" => Self::Application,
+                other => Self::Custom(other.to_string()),
+            }
+        }
+    }
+
+    impl Serialize for MimeType {
+        fn serialize<S: serde::Serializer>(
+            &self,
+            serializer: S,
+        ) -> std::result::Result<S::Ok, S::Error> {
+            serializer.serialize_str(self.as_str())
+        }
+    }
+
+    impl<'de> Deserialize<'de> for MimeType {
+        fn deserialize<D: serde::Deserializer<'de>>(
+            deserializer: D,
+        ) -> std::result::Result<Self, D::Error> {
+            Ok(Self::from(String::deserialize(deserializer)?.as_str()))
+        }
     }
 
     /// The only supported template engine is `freemarker-2.3.20`.
-    #[derive(Clone, Copy, Serialize, Deserialize)]
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
     pub enum TemplateEngine {
         /// freemarker-2.3.20.
+        #[default]
         #[serde(rename = "freemarker-2.3.20")]
         Freemarker,
     }